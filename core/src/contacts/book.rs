@@ -0,0 +1,131 @@
+use askama::Template;
+use url::Url;
+
+use std::error::Error;
+
+use crate::contacts::types::{
+    Contact, Contacts, Recipient, ResolvedRecipient, UnknownContactError,
+};
+use crate::storage;
+use crate::template::templates::ContactsTemplate;
+
+/// Data directory file with the address book.
+const CONTACTS_FILE: &str = "contacts.json";
+
+/// Whether a token can be a contact name. Names can't be mistaken for addresses.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether a payment proof address is a hex encoded ed25519 public key.
+pub fn is_valid_proof_address(address: &str) -> bool {
+    address.len() == 64 && address.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Looks up the address and proof address of a recipient.
+pub fn resolve(data_dir: &str, recipient: &Recipient) -> Result<ResolvedRecipient, Box<dyn Error>> {
+    match recipient {
+        Recipient::Url(url) => Ok(ResolvedRecipient {
+            name: None,
+            url: url.to_string(),
+            proof_address: None,
+        }),
+        Recipient::Name(name) => {
+            let contacts: Contacts = storage::load(data_dir, CONTACTS_FILE)?;
+            find(&contacts, name)
+        }
+    }
+}
+
+fn find(contacts: &Contacts, name: &str) -> Result<ResolvedRecipient, Box<dyn Error>> {
+    let name = name.to_lowercase();
+    match contacts.contacts.get(&name) {
+        Some(contact) => Ok(ResolvedRecipient {
+            name: Some(name),
+            url: contact.url.clone(),
+            proof_address: contact.proof_address.clone(),
+        }),
+        None => Err(Box::new(UnknownContactError(name))),
+    }
+}
+
+/// Adds or replaces a contact and returns the updated address book.
+pub fn add(
+    data_dir: &str,
+    name: &str,
+    url: &Url,
+    proof_address: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    let mut contacts: Contacts = storage::load(data_dir, CONTACTS_FILE)?;
+    contacts.contacts.insert(
+        name.to_lowercase(),
+        Contact {
+            url: url.to_string(),
+            proof_address,
+        },
+    );
+    storage::save(data_dir, CONTACTS_FILE, &contacts)?;
+    Ok(render_list(&contacts))
+}
+
+/// Removes a contact and returns the updated address book.
+pub fn remove(data_dir: &str, name: &str) -> Result<String, Box<dyn Error>> {
+    let mut contacts: Contacts = storage::load(data_dir, CONTACTS_FILE)?;
+    if contacts.contacts.remove(&name.to_lowercase()).is_none() {
+        return Ok(format!(
+            "There is no contact named {}.\n\n{}",
+            name,
+            render_list(&contacts)
+        ));
+    }
+    storage::save(data_dir, CONTACTS_FILE, &contacts)?;
+    Ok(render_list(&contacts))
+}
+
+/// Lists the address book.
+pub fn list(data_dir: &str) -> Result<String, Box<dyn Error>> {
+    let contacts: Contacts = storage::load(data_dir, CONTACTS_FILE)?;
+    Ok(render_list(&contacts))
+}
+
+fn render_list(contacts: &Contacts) -> String {
+    ContactsTemplate {
+        contacts: &contacts.contacts,
+    }
+    .render()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contact_names() {
+        assert!(is_valid_name("alice"));
+        assert!(is_valid_name("bob_2"));
+        assert!(!is_valid_name("http://alice.example"));
+        assert!(!is_valid_name(""));
+    }
+
+    #[test]
+    fn find_contact() {
+        let mut contacts = Contacts::default();
+        contacts.contacts.insert(
+            "alice".to_string(),
+            Contact {
+                url: "https://alice.example/".to_string(),
+                proof_address: None,
+            },
+        );
+
+        let alice = find(&contacts, "Alice").unwrap();
+        assert_eq!(alice.url, "https://alice.example/");
+        assert_eq!(alice.to_string(), "alice (https://alice.example/)");
+        assert!(find(&contacts, "bob").is_err());
+    }
+}
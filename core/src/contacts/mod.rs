@@ -0,0 +1,4 @@
+/// Address book commands
+pub mod book;
+/// Address book types
+pub mod types;
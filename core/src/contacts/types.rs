@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::collections::BTreeMap;
+use std::{error::Error, fmt};
+
+/// A named recipient.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Contact {
+    pub url: String,
+    /// Payment proof address of the recipient's wallet.
+    pub proof_address: Option<String>,
+}
+
+/// Address book, keyed by lowercase name.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Contacts {
+    pub contacts: BTreeMap<String, Contact>,
+}
+
+/// Destination of a send: an address, or the name of a contact.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipient {
+    Url(Url),
+    Name(String),
+}
+
+/// A recipient looked up in the address book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRecipient {
+    pub name: Option<String>,
+    pub url: String,
+    pub proof_address: Option<String>,
+}
+
+impl fmt::Display for ResolvedRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.url),
+            None => write!(f, "{}", self.url),
+        }
+    }
+}
+
+/// Name is not in the address book.
+#[derive(Debug)]
+pub struct UnknownContactError(pub String);
+
+impl Error for UnknownContactError {}
+
+impl fmt::Display for UnknownContactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "No contact named {}. Add one with /contact add {} <url>",
+            self.0, self.0
+        )
+    }
+}
@@ -0,0 +1,79 @@
+use askama::Template;
+use chrono::{DateTime, Duration, Utc};
+
+use std::error::Error;
+
+use crate::contacts::types::ResolvedRecipient;
+use crate::controller::types::PendingSend;
+use crate::security::limits;
+use crate::service::grin;
+use crate::service::types::GrinAmount;
+use crate::template::templates::ConfirmSendTemplate;
+use crate::types::Context;
+
+/// Estimates a send and describes it for confirmation. Nothing is locked or sent yet.
+pub fn prepare(
+    context: &Context,
+    amount: GrinAmount,
+    recipient: ResolvedRecipient,
+) -> Result<(String, PendingSend), Box<dyn Error>> {
+    // Refuse early rather than asking to confirm a send the limits won't allow.
+    limits::allowed(context, amount)?;
+
+    let fee = grin::estimate_fee(
+        amount,
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+    let info = grin::wallet_info(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+
+    let message = ConfirmSendTemplate {
+        amount: amount.as_grin(),
+        recipient: &recipient.to_string(),
+        fee,
+        balance_after: info.amount_currently_spendable - amount.as_grin() - fee,
+        timeout: context.confirm_timeout,
+    }
+    .render()
+    .unwrap();
+
+    let pending = PendingSend {
+        amount,
+        recipient,
+        created: Utc::now(),
+        verified: false,
+    };
+    Ok((message, pending))
+}
+
+/// Whether a pending send is too old to confirm.
+pub fn expired(pending: &PendingSend, timeout: u64, now: DateTime<Utc>) -> bool {
+    now.signed_duration_since(pending.created) > Duration::seconds(timeout as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_send_expires() {
+        let now = Utc::now();
+        let pending = PendingSend {
+            amount: GrinAmount::new(1.0),
+            recipient: ResolvedRecipient {
+                name: None,
+                url: "https://alice.example/".to_string(),
+                proof_address: None,
+            },
+            created: now - Duration::seconds(90),
+            verified: false,
+        };
+        assert!(!expired(&pending, 120, now));
+        assert!(expired(&pending, 60, now));
+    }
+}
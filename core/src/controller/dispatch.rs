@@ -1,15 +1,154 @@
 use askama::Template;
-use log::Level;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, warn, Level};
 
-use crate::controller::types::{Action, Screen, SendCommand, State};
+use crate::contacts::book;
+use crate::controller::confirm;
+use crate::controller::types::{
+    Action, AlertCommand, CommandParseError, ContactCommand, DigestCommand, ScheduleCommand,
+    Screen, SendCommand, State, TxCommand,
+};
+use crate::diagnostics::status;
+use crate::monitor::{alerts, digest, schedule, watch};
+use crate::security::roles::{self, Users};
+use crate::security::types::{FrozenError, ReadOnlyError};
+use crate::security::{backup, freeze, limits, seed, session, totp};
 use crate::service::grin;
-use crate::template::templates::{HelpTemplate, SeedTemplate};
+use crate::template::templates::HelpTemplate;
 
-use crate::service::types::GrinAmount;
+use crate::service::types::{GrinAmount, MnemonicLength};
 
 /// Main UI reducer: Returns a new State from an Action.
 pub fn screen_reducer(state: &State, action: &Action) -> State {
-    let s = state.clone();
+    // Commands from chat carry the user who sent them.
+    let (sender, action) = match action {
+        Action::SentBy(user, inner) => (Some(user.clone()), inner.as_ref()),
+        _ => (None, action),
+    };
+
+    // Buttons only apply to the message they were sent with.
+    let s = State {
+        buttons: vec![],
+        expires: None,
+        sender,
+        ..state.clone()
+    };
+
+    // A locked session only allows /help and /unlock. Each user unlocks their own.
+    let mut s = s;
+    if s.context.pin_hash.is_some() {
+        let now = Utc::now();
+        let user_session = s.sessions.entry(s.sender.clone()).or_default();
+        if user_session.is_locked(now) && !session::allowed_while_locked(action) {
+            return State {
+                screen: Screen::Locked,
+                id: Some(action.id()),
+                message: Some(
+                    "Grin Bot is locked. Unlock with /unlock followed by your PIN.".to_string(),
+                ),
+                error_level: None,
+                ..s
+            };
+        }
+        match action {
+            Action::Tick(_) => {}
+            _ => session::touch(user_session, &s.context, now),
+        }
+    }
+
+    // Read-only mode leaves only viewing commands, and /freeze.
+    if s.context.read_only && !roles::allowed_read_only(action) {
+        warn!("Refused {:?} in read-only mode", action);
+        return State {
+            id: Some(action.id()),
+            message: Some(format!("{}.", ReadOnlyError)),
+            error_level: Some(Level::Warn),
+            ..s
+        };
+    }
+
+    // While frozen, spending is refused and every reply carries a notice.
+    if let Some(since) = freeze::frozen_since(&s.context.data_dir) {
+        if !is_tick_or_freeze(action) {
+            let notice = FrozenError(since).to_string();
+            if freeze::moves_funds(action) {
+                warn!("Refused {:?} while frozen", action);
+                return State {
+                    id: Some(action.id()),
+                    message: Some(format!("{}.", notice)),
+                    error_level: Some(Level::Warn),
+                    ..s
+                };
+            }
+            warn!("Command from chat {} while frozen", action.id());
+            let next = reduce(s, action);
+            let message = match next.message {
+                Some(ref msg) => format!("{}.\n\n{}", notice, msg),
+                None => format!("{}.", notice),
+            };
+            return State {
+                message: Some(message),
+                ..next
+            };
+        }
+    }
+
+    // Hold value-moving actions until an authenticator code arrives.
+    if let Some(id) = code_required(&s, action) {
+        s.awaiting_code
+            .insert(key(&s, id), (action.clone(), Utc::now()));
+        return State {
+            id: Some(id),
+            message: Some("Enter the 6-digit code from your authenticator app.".to_string()),
+            error_level: None,
+            ..s
+        };
+    }
+    reduce(s, action)
+}
+
+/// Chat id of an action that needs an authenticator code first, if any.
+fn code_required(state: &State, action: &Action) -> Option<i64> {
+    state.context.totp_secret.as_ref()?;
+    match action {
+        Action::Confirm(id) => match state.pending.get(&key(state, *id)) {
+            Some(pending) if !pending.verified => Some(*id),
+            _ => None,
+        },
+        Action::Cancel(id, _) | Action::AddSchedule(id, ..) | Action::ApproveSchedule(id, _) => {
+            Some(*id)
+        }
+        _ => None,
+    }
+}
+
+/// Key of the pending send or held action of the current sender in a chat.
+fn key(state: &State, id: i64) -> (i64, Option<String>) {
+    (id, state.sender.clone())
+}
+
+/// Whether an action can take a trailing authenticator code.
+fn accepts_code(action: &Action) -> bool {
+    match action {
+        Action::Send(..)
+        | Action::Confirm(_)
+        | Action::Cancel(..)
+        | Action::AddSchedule(..)
+        | Action::ApproveSchedule(..) => true,
+        _ => false,
+    }
+}
+
+/// Ticks and /freeze get no frozen notice.
+fn is_tick_or_freeze(action: &Action) -> bool {
+    match action {
+        Action::Tick(_) | Action::Freeze(_) => true,
+        _ => false,
+    }
+}
+
+/// Returns a new State from an Action that needs no further checks.
+fn reduce(s: State, action: &Action) -> State {
     match action {
         Action::Home(id) => State {
             prev_screen: Screen::Home,
@@ -18,26 +157,169 @@ pub fn screen_reducer(state: &State, action: &Action) -> State {
             message: None,
             context: s.context,
             error_level: None,
+            buttons: vec![],
+            sender: s.sender,
+            pending: s.pending,
+            awaiting_code: s.awaiting_code,
+            sessions: s.sessions,
+            expires: None,
+            backup_quiz: s.backup_quiz,
         },
-        Action::Create(id) => {
-            let (message, error_level) =
-                match grin::new_wallet(&s.context.wallet_dir, &s.context.wallet_password) {
-                    Ok(seed) => (SeedTemplate { seed: &seed }.render().unwrap(), None),
-                    Err(e) => (format!("Error: {}", e), Some(Level::Error)),
-                };
+        Action::Create(id, length) => {
+            let (message, error_level, expires, backup_quiz) = match grin::new_wallet(
+                &s.context.wallet_dir,
+                &s.context.wallet_password,
+                length.unwrap_or(s.context.mnemonic_length),
+                &s.context.owner_endpoint,
+                &s.context.http_client,
+            ) {
+                Ok(seed) => {
+                    let (message, expires) = seed::deliver(&s.context, &seed);
+                    match backup::quiz(&s.context.data_dir, &seed) {
+                        Ok(quiz) => {
+                            let message = format!("{}\n\n{}", message, backup::prompt(&quiz));
+                            (message, None, expires, Some(quiz))
+                        }
+                        Err(e) => {
+                            error!("Can't start the recovery phrase backup check: {}", e);
+                            (message, None, expires, None)
+                        }
+                    }
+                }
+                Err(e) => (format!("Error: {}", e), Some(Level::Error), None, None),
+            };
 
             State {
                 screen: Screen::Create,
                 id: Some(*id),
                 message: Some(message),
                 error_level,
+                expires,
+                backup_quiz: backup_quiz.or(s.backup_quiz),
                 ..s
             }
         }
-        Action::Send(id, amount, destination) => {
-            let (message, error_level) = match grin::send(
-                *amount,
-                destination.as_str(),
+        Action::Backup(id, words) => {
+            let mut s = s;
+            let (message, error_level) = match &s.backup_quiz {
+                None if backup::pending(&s.context.data_dir) => (
+                    "Grin Bot restarted since /create and no longer has the recovery phrase to check. Check your written copy, then run <pre>grinbot backup-verified</pre> on the host.".to_string(),
+                    Some(Level::Info),
+                ),
+                None => (
+                    "Nothing to verify. /backup checks the recovery phrase from /create.".to_string(),
+                    None,
+                ),
+                Some(quiz) if words.is_empty() => (backup::prompt(quiz), None),
+                Some(quiz) => match backup::verify(&s.context.data_dir, quiz, words) {
+                    Ok(true) => {
+                        s.backup_quiz = None;
+                        (
+                            "Backup verified. Keep the recovery phrase somewhere safe and offline."
+                                .to_string(),
+                            None,
+                        )
+                    }
+                    Ok(false) => (
+                        format!("Those words don't match.\n\n{}", backup::prompt(quiz)),
+                        Some(Level::Info),
+                    ),
+                    Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+                },
+            };
+
+            State {
+                screen: Screen::Backup,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Send(id, amount, recipient) => {
+            match backup::check_send(&s.context)
+                .map_err(|e| e.into())
+                .and_then(|reminder| {
+                    book::resolve(&s.context.data_dir, recipient)
+                        .and_then(|recipient| confirm::prepare(&s.context, *amount, recipient))
+                        .map(|(message, pending)| match reminder {
+                            Some(reminder) => (format!("{}\n\n{}", reminder, message), pending),
+                            None => (message, pending),
+                        })
+                })
+            {
+                Ok((message, pending)) => {
+                    let mut s = s;
+                    s.pending.insert(key(&s, *id), pending);
+                    State {
+                        screen: Screen::Confirm,
+                        id: Some(*id),
+                        message: Some(message),
+                        error_level: None,
+                        buttons: vec![vec!["/confirm".to_string(), "/abort".to_string()]],
+                        ..s
+                    }
+                }
+                Err(e) => State {
+                    screen: Screen::Send,
+                    id: Some(*id),
+                    message: Some(format!("Error: {}", e)),
+                    error_level: Some(Level::Info),
+                    ..s
+                },
+            }
+        }
+        Action::Confirm(id) => {
+            let mut s = s;
+            let (message, error_level) = match s.pending.remove(&key(&s, *id)) {
+                None => (
+                    "Nothing to confirm. Start a send with /send.".to_string(),
+                    None,
+                ),
+                Some(ref pending)
+                    if confirm::expired(pending, s.context.confirm_timeout, Utc::now()) =>
+                {
+                    (
+                        "The send expired and was not sent. Start again with /send.".to_string(),
+                        None,
+                    )
+                }
+                Some(pending) => {
+                    match limits::send(&s.context, pending.amount, &pending.recipient) {
+                        Ok(msg) => (
+                            format!("Success:\n  to: {}\n{}", pending.recipient, msg),
+                            None,
+                        ),
+                        Err(e) => (format!("Error: {}", e), Some(Level::Info)),
+                    }
+                }
+            };
+
+            State {
+                screen: Screen::Send,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Abort(id) => {
+            let mut s = s;
+            let message = match s.pending.remove(&key(&s, *id)) {
+                Some(_) => "Send aborted. Nothing was sent.",
+                None => "Nothing to abort.",
+            };
+
+            State {
+                screen: Screen::Home,
+                id: Some(*id),
+                message: Some(message.to_string()),
+                error_level: None,
+                ..s
+            }
+        }
+        Action::Balance(id) => {
+            let (message, error_level) = match grin::balance(
                 &s.context.wallet_dir,
                 &s.context.owner_endpoint,
                 &s.context.http_client,
@@ -47,15 +329,41 @@ pub fn screen_reducer(state: &State, action: &Action) -> State {
             };
 
             State {
-                screen: Screen::Send,
+                screen: Screen::Balance,
                 id: Some(*id),
                 message: Some(message),
                 error_level,
                 ..s
             }
         }
-        Action::Balance(id) => {
-            let (message, error_level) = match grin::balance(
+        Action::Status(id) => State {
+            screen: Screen::Status,
+            id: Some(*id),
+            message: Some(status::report(&s.context)),
+            error_level: None,
+            ..s
+        },
+        Action::Address(id) => {
+            let (message, error_level) = match grin::proof_address(
+                &s.context.wallet_dir,
+                &s.context.owner_endpoint,
+                &s.context.http_client,
+            ) {
+                Ok(address) => (format!("Payment proof address:\n<pre>{}</pre>", address), None),
+                Err(e) => (format!("Error: {}", e), Some(Level::Info)),
+            };
+
+            State {
+                screen: Screen::Address,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Repost(id, tx_id) => {
+            let (message, error_level) = match grin::repost_tx(
+                *tx_id,
                 &s.context.wallet_dir,
                 &s.context.owner_endpoint,
                 &s.context.http_client,
@@ -65,7 +373,216 @@ pub fn screen_reducer(state: &State, action: &Action) -> State {
             };
 
             State {
-                screen: Screen::Balance,
+                screen: Screen::Repost,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Cancel(id, tx_id) => {
+            let (message, error_level) = match grin::cancel_tx(
+                *tx_id,
+                &s.context.wallet_dir,
+                &s.context.owner_endpoint,
+                &s.context.http_client,
+            ) {
+                Ok(msg) => (format!("Success:\n{}", msg), None),
+                Err(e) => (format!("Error: {}", e), Some(Level::Info)),
+            };
+
+            State {
+                screen: Screen::Cancel,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::AddAlert(id, direction, threshold) => {
+            let (message, error_level) =
+                match alerts::add(&s.context.data_dir, *direction, *threshold) {
+                    Ok(msg) => (msg, Some(Level::Info)),
+                    Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+                };
+
+            State {
+                screen: Screen::Alerts,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::RemoveAlert(id, number) => {
+            let (message, error_level) = match alerts::remove(&s.context.data_dir, *number) {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Alerts,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Alerts(id) => {
+            let (message, error_level) = match alerts::list(&s.context.data_dir) {
+                Ok(msg) => (msg, None),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Alerts,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Digest(id) => {
+            let (message, error_level) = match digest::now(&s.context) {
+                Ok(msg) => (msg, None),
+                Err(e) => (format!("Error: {}", e), Some(Level::Info)),
+            };
+
+            State {
+                screen: Screen::Digest,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::SetDigest(id, schedule) => {
+            let (message, error_level) = match digest::set_schedule(&s.context.data_dir, *schedule)
+            {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Digest,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+
+        Action::AddSchedule(id, amount, recipient, frequency) => {
+            let (message, error_level) = match book::resolve(&s.context.data_dir, recipient)
+                .and_then(|recipient| {
+                    schedule::add(&s.context.data_dir, *amount, &recipient, *frequency)
+                }) {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Schedule,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::RemoveSchedule(id, number) => {
+            let (message, error_level) = match schedule::remove(&s.context.data_dir, *number) {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Schedule,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::ApproveSchedule(id, number) => {
+            let (message, error_level) = match schedule::approve(&s.context, *number) {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Schedule,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::SkipSchedule(id, number) => {
+            let (message, error_level) = match schedule::skip(&s.context.data_dir, *number) {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Schedule,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Schedules(id) => {
+            let (message, error_level) = match schedule::list(&s.context.data_dir) {
+                Ok(msg) => (msg, None),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Schedule,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+
+        Action::AddContact(id, name, url, proof_address) => {
+            let (message, error_level) =
+                match book::add(&s.context.data_dir, name, url, proof_address.clone()) {
+                    Ok(msg) => (msg, Some(Level::Info)),
+                    Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+                };
+
+            State {
+                screen: Screen::Contacts,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::RemoveContact(id, name) => {
+            let (message, error_level) = match book::remove(&s.context.data_dir, name) {
+                Ok(msg) => (msg, Some(Level::Info)),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Contacts,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Contacts(id) => {
+            let (message, error_level) = match book::list(&s.context.data_dir) {
+                Ok(msg) => (msg, None),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Contacts,
                 id: Some(*id),
                 message: Some(message),
                 error_level,
@@ -85,15 +602,22 @@ pub fn screen_reducer(state: &State, action: &Action) -> State {
         }
         Action::NoUsername(id) => State {
             id: Some(*id),
-            message: Some("You must have a username to use Grin Bot.".into()),
+            message: Some("Grin Bot could not tell who sent this message.".into()),
             error_level: Some(Level::Warn),
             ..s
         },
         Action::WrongUsername(id) => State {
             id: Some(*id),
-            message: Some(
-                "Your username does not match the username in the Grin Bot config.".into(),
-            ),
+            message: Some("You are not a user in the Grin Bot config.".into()),
+            error_level: Some(Level::Warn),
+            ..s
+        },
+        Action::NotPermitted(id, role, required) => State {
+            id: Some(*id),
+            message: Some(format!(
+                "Your role is {}. This command needs the {} role.",
+                role, required
+            )),
             error_level: Some(Level::Warn),
             ..s
         },
@@ -101,7 +625,7 @@ pub fn screen_reducer(state: &State, action: &Action) -> State {
         Action::ModeNotSupported(id) => State {
             id: Some(*id),
             message: Some(
-                "For security reasons, inline and group messages are not supported.".into(),
+                "For security reasons, inline messages and group chats not in the Grin Bot config are not supported.".into(),
             ),
             error_level: Some(Level::Warn),
             ..s
@@ -125,9 +649,152 @@ pub fn screen_reducer(state: &State, action: &Action) -> State {
             error_level: Some(Level::Error),
             ..s
         },
+        Action::Tick(id) => {
+            // Drop sends that were never confirmed and actions that never got a code.
+            let now = Utc::now();
+            let timeout = s.context.confirm_timeout;
+            let pending = s
+                .pending
+                .clone()
+                .into_iter()
+                .filter(|(_, pending)| !confirm::expired(pending, timeout, now))
+                .collect();
+            let awaiting_code = s
+                .awaiting_code
+                .clone()
+                .into_iter()
+                .filter(|(_, (_, held))| !code_expired(*held, timeout, now))
+                .collect();
+            let s = State {
+                pending,
+                awaiting_code,
+                ..s
+            };
+
+            match watch::run(&s.context) {
+                Some(notice) => State {
+                    id: Some(*id),
+                    message: Some(notice.message),
+                    error_level: None,
+                    buttons: notice.buttons,
+                    ..s
+                },
+                None => State {
+                    id: Some(*id),
+                    message: None,
+                    error_level: None,
+                    ..s
+                },
+            }
+        }
+        Action::Unlock(id, pin) => {
+            let mut s = s;
+            let user_session = s.sessions.entry(s.sender.clone()).or_default();
+            let (message, error_level) =
+                match session::unlock(user_session, &s.context, pin, Utc::now()) {
+                    Ok(msg) => (msg, Some(Level::Info)),
+                    Err(msg) => (msg, Some(Level::Warn)),
+                };
+
+            State {
+                screen: Screen::Home,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::Lock(id) => {
+            let mut s = s;
+            if let Some(user_session) = s.sessions.get_mut(&s.sender) {
+                user_session.unlocked_until = None;
+            }
+            let message = match s.context.pin_hash {
+                Some(_) => "Locked. Unlock with /unlock followed by your PIN.",
+                None => "Grin Bot has no PIN lock.",
+            };
+            State {
+                screen: Screen::Locked,
+                id: Some(*id),
+                message: Some(message.to_string()),
+                error_level: None,
+                ..s
+            }
+        }
+        Action::Freeze(id) => {
+            let (message, error_level) = match freeze::freeze(&s.context.data_dir) {
+                Ok(_) => (
+                    "Frozen. Spending is disabled until grinbot unfreeze is run on the host."
+                        .to_string(),
+                    Some(Level::Warn),
+                ),
+                Err(e) => (format!("Error: {}", e), Some(Level::Error)),
+            };
+
+            State {
+                screen: Screen::Home,
+                id: Some(*id),
+                message: Some(message),
+                error_level,
+                ..s
+            }
+        }
+        Action::WithCode(id, code, inner) => match totp::check(&s.context, code) {
+            Ok(()) => {
+                let mut next = reduce(s, inner);
+                // A code given with /send also covers its /confirm.
+                if let Action::Send(..) = **inner {
+                    if let Some(pending) = next.pending.get_mut(&key(&next, *id)) {
+                        pending.verified = true;
+                    }
+                }
+                next
+            }
+            Err(e) => State {
+                id: Some(*id),
+                message: Some(format!("Error: {}", e)),
+                error_level: Some(Level::Warn),
+                ..s
+            },
+        },
+        Action::Code(id, code) => {
+            let mut s = s;
+            let timeout = s.context.confirm_timeout;
+            match s.awaiting_code.remove(&key(&s, *id)) {
+                Some((ref held, created)) if !code_expired(created, timeout, Utc::now()) => {
+                    match totp::check(&s.context, code) {
+                        Ok(()) => reduce(s, held),
+                        Err(e) => State {
+                            id: Some(*id),
+                            message: Some(format!("Error: {}\n\nNothing was done.", e)),
+                            error_level: Some(Level::Warn),
+                            ..s
+                        },
+                    }
+                }
+                _ => State {
+                    id: Some(*id),
+                    message: Some("Nothing is waiting for an authenticator code.".to_string()),
+                    error_level: None,
+                    ..s
+                },
+            }
+        }
+        Action::SentBy(user, inner) => reduce(
+            State {
+                sender: Some(user.clone()),
+                ..s
+            },
+            inner,
+        ),
     }
 }
 
+/// Whether an action held for an authenticator code is too old to run.
+fn code_expired(held: DateTime<Utc>, timeout: u64, now: DateTime<Utc>) -> bool {
+    now.signed_duration_since(held) > Duration::seconds(timeout as i64)
+}
+
 /// Splits command into type and parameters.
 pub fn tokenize_command(raw_command: &str) -> (&str, Vec<&str>) {
     let message_tokens: Vec<&str> = raw_command.split(" ").collect();
@@ -139,15 +806,22 @@ pub fn get_action(
     id: i64,
     message_from_user: &Option<String>,
     message: Option<String>,
-    config_user: &str,
+    users: &Users,
 ) -> Action {
     if let Some(msg) = message {
         let (command_type, parameters) = tokenize_command(&msg);
         // Check for username before looking at command
-        match get_username_action(id, message_from_user, config_user) {
-            Some(action) => action,
-            None => get_command(command_type, id, parameters),
+        if let Some(action) = get_username_action(id, message_from_user, users) {
+            return action;
         }
+        let action = get_command(command_type, id, parameters);
+        // Known user, so the role is there.
+        let role = users[message_from_user.as_ref().unwrap()];
+        let required = roles::required(&action);
+        if role < required {
+            return Action::NotPermitted(id, role, required);
+        }
+        action
     } else {
         Action::Unknown(id)
     }
@@ -162,18 +836,118 @@ pub fn get_action(
 /// get_command("/send", 99, vec!["0.01", "http://recipient123.org"]);
 /// ```
 pub fn get_command(command_type: &str, id: i64, command: Vec<&str>) -> Action {
+    // An authenticator code sent on its own answers a prompt.
+    if totp::is_code(command_type) && command.is_empty() {
+        return Action::Code(id, command_type.to_string());
+    }
+
+    let action = parse_command(command_type, id, command.clone());
+
+    // A command that moves funds may end with an authenticator code.
+    if let Action::CommandError(..) = action {
+        if let Some((code, rest)) = command.split_last() {
+            if totp::is_code(code) {
+                let inner = parse_command(command_type, id, rest.to_vec());
+                if accepts_code(&inner) {
+                    return Action::WithCode(id, code.to_string(), Box::new(inner));
+                }
+            }
+        }
+    }
+    action
+}
+
+/// Parses a command without a trailing authenticator code.
+fn parse_command(command_type: &str, id: i64, command: Vec<&str>) -> Action {
     match command_type {
         "/home" => Action::Home(id),
-        "/create" => Action::Create(id),
+        "/create" => match command.as_slice() {
+            [] => Action::Create(id, None),
+            [words] => match words.parse().ok().and_then(|w| MnemonicLength::new(w).ok()) {
+                Some(length) => Action::Create(id, Some(length)),
+                None => Action::CommandError(id, CommandParseError::MnemonicLengthParseError),
+            },
+            _ => Action::CommandError(
+                id,
+                CommandParseError::WrongNumberOfArgsError(
+                    "Wrong number of arguments.\n\nUsage: <pre>/create</pre> <pre>/create 12</pre>"
+                        .to_string(),
+                ),
+            ),
+        },
+        "/backup" => Action::Backup(id, command.iter().map(|w| w.to_string()).collect()),
         "/send" => match SendCommand::parse(command) {
             Ok(send_command) => {
                 let amount = GrinAmount::new(send_command.amount);
-                let url = send_command.destination.unwrap();
-                Action::Send(id, amount, url)
+                let recipient = send_command.destination.unwrap();
+                Action::Send(id, amount, recipient)
             }
             Err(error) => Action::CommandError(id, error),
         },
+        "/confirm" => match command.len() {
+            0 => Action::Confirm(id),
+            _ => Action::CommandError(
+                id,
+                CommandParseError::WrongNumberOfArgsError(
+                    "Wrong number of arguments.\n\nUsage: <pre>/confirm</pre> <pre>/confirm 123456</pre>"
+                        .to_string(),
+                ),
+            ),
+        },
+        "/abort" => Action::Abort(id),
         "/balance" => Action::Balance(id),
+        "/status" => Action::Status(id),
+        "/address" => Action::Address(id),
+        "/repost" => match TxCommand::parse(command_type, command) {
+            Ok(tx_command) => Action::Repost(id, tx_command.tx_id),
+            Err(error) => Action::CommandError(id, error),
+        },
+        "/cancel" => match TxCommand::parse(command_type, command) {
+            Ok(tx_command) => Action::Cancel(id, tx_command.tx_id),
+            Err(error) => Action::CommandError(id, error),
+        },
+        "/alert" => match AlertCommand::parse(command) {
+            Ok(AlertCommand::Add(direction, threshold)) => {
+                Action::AddAlert(id, direction, threshold)
+            }
+            Ok(AlertCommand::Remove(number)) => Action::RemoveAlert(id, number),
+            Err(error) => Action::CommandError(id, error),
+        },
+        "/alerts" => Action::Alerts(id),
+        "/digest" => match DigestCommand::parse(command) {
+            Ok(DigestCommand::Now) => Action::Digest(id),
+            Ok(DigestCommand::Set(schedule)) => Action::SetDigest(id, schedule),
+            Err(error) => Action::CommandError(id, error),
+        },
+        "/schedule" => match ScheduleCommand::parse(command) {
+            Ok(ScheduleCommand::Add(amount, recipient, frequency)) => {
+                Action::AddSchedule(id, GrinAmount::new(amount), recipient, frequency)
+            }
+            Ok(ScheduleCommand::List) => Action::Schedules(id),
+            Ok(ScheduleCommand::Remove(number)) => Action::RemoveSchedule(id, number),
+            Ok(ScheduleCommand::Approve(number)) => Action::ApproveSchedule(id, number),
+            Ok(ScheduleCommand::Skip(number)) => Action::SkipSchedule(id, number),
+            Err(error) => Action::CommandError(id, error),
+        },
+        "/contacts" => Action::Contacts(id),
+        "/contact" => match ContactCommand::parse(command) {
+            Ok(ContactCommand::Add(name, url, proof_address)) => {
+                Action::AddContact(id, name, url, proof_address)
+            }
+            Ok(ContactCommand::Remove(name)) => Action::RemoveContact(id, name),
+            Err(error) => Action::CommandError(id, error),
+        },
+        "/unlock" => match command.as_slice() {
+            [pin] => Action::Unlock(id, pin.to_string()),
+            _ => Action::CommandError(
+                id,
+                CommandParseError::WrongNumberOfArgsError(
+                    "Wrong number of arguments.\n\nUsage: <pre>/unlock 1234</pre>".to_string(),
+                ),
+            ),
+        },
+        "/lock" => Action::Lock(id),
+        "/freeze" => Action::Freeze(id),
         "/help" => Action::Help(id),
         "/start" => Action::Help(id),
         "/back" => Action::Back(id),
@@ -186,12 +960,12 @@ pub fn get_command(command_type: &str, id: i64, command: Vec<&str>) -> Action {
 pub fn get_username_action(
     id: i64,
     message_from_user: &Option<String>,
-    config_user: &str,
+    users: &Users,
 ) -> Option<Action> {
     match message_from_user {
         None => Some(Action::NoUsername(id)),
         Some(username) => {
-            if username != config_user {
+            if !users.contains_key(username) {
                 Some(Action::WrongUsername(id))
             } else {
                 None
@@ -205,6 +979,7 @@ mod tests {
     use super::*;
     use crate::controller::dispatch::get_command;
     use crate::controller::types::{Action, SendCommand};
+    use crate::security::types::Role;
 
     #[test]
     fn home_command() {
@@ -218,6 +993,18 @@ mod tests {
         assert_eq!(command, Action::Balance(99));
     }
 
+    #[test]
+    fn address_command() {
+        let command = get_command("/address", 99, vec![]);
+        assert_eq!(command, Action::Address(99));
+    }
+
+    #[test]
+    fn status_command() {
+        let command = get_command("/status", 99, vec![]);
+        assert_eq!(command, Action::Status(99));
+    }
+
     #[test]
     fn unknown_command() {
         let command = get_command("/abcd", 100, vec![]);
@@ -226,24 +1013,33 @@ mod tests {
 
     #[test]
     fn no_username() {
-        let command = get_username_action(101, &None, &"user123".to_string());
+        let users = vec![("user123".to_string(), Role::Admin)]
+            .into_iter()
+            .collect();
+        let command = get_username_action(101, &None, &users);
         assert_eq!(command, Some(Action::NoUsername(101)));
     }
 
     #[test]
     fn wrong_username() {
-        let command =
-            get_username_action(101, &Some("user321".to_string()), &"user123".to_string());
+        let users = vec![("user123".to_string(), Role::Admin)]
+            .into_iter()
+            .collect();
+        let command = get_username_action(101, &Some("user321".to_string()), &users);
         assert_eq!(command, Some(Action::WrongUsername(101)));
     }
 
     #[test]
     fn send_command() {
+        use crate::contacts::types::Recipient;
         use url::Url;
 
         let command = get_command("/send", 102, vec!["0.01", "https://recipient123.org"]);
         let url = Url::parse("https://recipient123.org").ok().unwrap();
-        assert_eq!(command, Action::Send(102, GrinAmount::new(0.01), url));
+        assert_eq!(
+            command,
+            Action::Send(102, GrinAmount::new(0.01), Recipient::Url(url))
+        );
     }
 
     #[test]
@@ -266,4 +1062,439 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cancel_command() {
+        let command = get_command("/cancel", 104, vec!["12"]);
+        assert_eq!(command, Action::Cancel(104, 12));
+    }
+
+    #[test]
+    fn bad_tx_id_repost_command() {
+        use crate::controller::types::CommandParseError::*;
+        let command = get_command("/repost", 105, vec!["abc"]);
+        assert_eq!(command, Action::CommandError(105, TxIdParseError));
+    }
+
+    #[test]
+    fn alert_command() {
+        use crate::monitor::types::AlertDirection;
+        let command = get_command("/alert", 106, vec!["below", "5"]);
+        assert_eq!(command, Action::AddAlert(106, AlertDirection::Below, 5.0));
+        let command = get_command("/alert", 106, vec!["remove", "2"]);
+        assert_eq!(command, Action::RemoveAlert(106, 2));
+    }
+
+    #[test]
+    fn bad_alert_command() {
+        use crate::controller::types::CommandParseError::*;
+        let command = get_command("/alert", 107, vec!["sideways", "5"]);
+        assert_eq!(
+            command,
+            Action::CommandError(107, WrongNumberOfArgsError(AlertCommand::usage()))
+        );
+        let command = get_command("/alert", 107, vec!["above", "lots"]);
+        assert_eq!(command, Action::CommandError(107, AmountParseError));
+    }
+
+    #[test]
+    fn digest_command() {
+        use crate::monitor::types::DigestSchedule;
+        assert_eq!(get_command("/digest", 108, vec![]), Action::Digest(108));
+        assert_eq!(
+            get_command("/digest", 108, vec!["weekly", "18:30"]),
+            Action::SetDigest(108, DigestSchedule::parse("weekly", "18:30"))
+        );
+        assert_eq!(
+            get_command("/digest", 108, vec!["off"]),
+            Action::SetDigest(108, None)
+        );
+    }
+
+    #[test]
+    fn schedule_command() {
+        use crate::contacts::types::Recipient;
+        use crate::controller::types::CommandParseError;
+        use crate::monitor::types::Frequency;
+        use url::Url;
+
+        assert_eq!(
+            get_command(
+                "/schedule",
+                109,
+                vec!["send", "2.5", "https://alice.example", "monthly", "1"]
+            ),
+            Action::AddSchedule(
+                109,
+                GrinAmount::new(2.5),
+                Recipient::Url(Url::parse("https://alice.example").unwrap()),
+                Frequency::Monthly(1)
+            )
+        );
+        assert_eq!(
+            get_command("/schedule", 109, vec!["list"]),
+            Action::Schedules(109)
+        );
+        assert_eq!(
+            get_command("/schedule", 109, vec!["approve", "2"]),
+            Action::ApproveSchedule(109, 2)
+        );
+        assert_eq!(
+            get_command(
+                "/schedule",
+                109,
+                vec!["send", "2.5", "https://alice.example", "yearly"]
+            ),
+            Action::CommandError(109, CommandParseError::ScheduleParseError)
+        );
+    }
+
+    #[test]
+    fn contact_send_command() {
+        use crate::contacts::types::Recipient;
+
+        let command = get_command("/send", 110, vec!["1", "alice"]);
+        assert_eq!(
+            command,
+            Action::Send(
+                110,
+                GrinAmount::new(1.0),
+                Recipient::Name("alice".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn contact_command() {
+        use crate::controller::types::CommandParseError::*;
+        use url::Url;
+
+        let proof_address = "a".repeat(64);
+        assert_eq!(
+            get_command(
+                "/contact",
+                111,
+                vec!["add", "alice", "https://alice.example", &proof_address]
+            ),
+            Action::AddContact(
+                111,
+                "alice".to_string(),
+                Url::parse("https://alice.example").unwrap(),
+                Some(proof_address.clone())
+            )
+        );
+        assert_eq!(
+            get_command("/contact", 111, vec!["remove", "alice"]),
+            Action::RemoveContact(111, "alice".to_string())
+        );
+        assert_eq!(
+            get_command("/contact", 111, vec!["add", "a/b", "https://alice.example"]),
+            Action::CommandError(111, ContactNameParseError)
+        );
+        assert_eq!(
+            get_command(
+                "/contact",
+                111,
+                vec!["add", "alice", "https://alice.example", "xyz"]
+            ),
+            Action::CommandError(111, ProofAddressParseError)
+        );
+    }
+
+    #[test]
+    fn confirm_commands() {
+        assert_eq!(get_command("/confirm", 112, vec![]), Action::Confirm(112));
+        assert_eq!(get_command("/abort", 112, vec![]), Action::Abort(112));
+    }
+
+    #[test]
+    fn abort_and_expire_pending_send() {
+        use crate::contacts::types::ResolvedRecipient;
+        use crate::controller::types::{PendingSend, State};
+        use chrono::{Duration, Utc};
+
+        let pending = PendingSend {
+            amount: GrinAmount::new(1.0),
+            recipient: ResolvedRecipient {
+                name: None,
+                url: "https://alice.example/".to_string(),
+                proof_address: None,
+            },
+            created: Utc::now() - Duration::seconds(600),
+            verified: false,
+        };
+        let mut state = State::default();
+        state.pending.insert((113, None), pending.clone());
+        state.pending.insert((114, None), pending);
+
+        // Pending sends belong to one chat.
+        let state = screen_reducer(&state, &Action::Abort(113));
+        assert_eq!(
+            state.message,
+            Some("Send aborted. Nothing was sent.".to_string())
+        );
+        assert!(state.pending.contains_key(&(114, None)));
+
+        let state = screen_reducer(&state, &Action::Confirm(114));
+        assert_eq!(
+            state.message,
+            Some("The send expired and was not sent. Start again with /send.".to_string())
+        );
+        assert!(state.pending.is_empty());
+    }
+
+    #[test]
+    fn pending_sends_belong_to_their_sender() {
+        use crate::contacts::types::ResolvedRecipient;
+        use crate::controller::types::{PendingSend, State};
+        use chrono::Utc;
+
+        let alice = Some("alice".to_string());
+        let bob = Some("bob".to_string());
+        let mut state = State::default();
+        state.pending.insert(
+            (130, alice.clone()),
+            PendingSend {
+                amount: GrinAmount::new(1.0),
+                recipient: ResolvedRecipient {
+                    name: None,
+                    url: "https://carol.example/".to_string(),
+                    proof_address: None,
+                },
+                created: Utc::now(),
+                verified: false,
+            },
+        );
+
+        // Someone else in the same group can neither confirm nor abort it.
+        let state = screen_reducer(&state, &Action::Confirm(130).sent_by(&bob));
+        assert_eq!(
+            state.message,
+            Some("Nothing to confirm. Start a send with /send.".to_string())
+        );
+        let state = screen_reducer(&state, &Action::Abort(130).sent_by(&bob));
+        assert_eq!(state.message, Some("Nothing to abort.".to_string()));
+        assert!(state.pending.contains_key(&(130, alice.clone())));
+
+        let state = screen_reducer(&state, &Action::Abort(130).sent_by(&alice));
+        assert_eq!(
+            state.message,
+            Some("Send aborted. Nothing was sent.".to_string())
+        );
+        assert!(state.pending.is_empty());
+
+        // Nor give the code for an action held for someone else.
+        let mut state = state;
+        state.context.totp_secret = Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string());
+        let state = screen_reducer(&state, &Action::Cancel(130, 12).sent_by(&alice));
+        let state = screen_reducer(
+            &state,
+            &Action::Code(130, "123456".to_string()).sent_by(&bob),
+        );
+        assert_eq!(
+            state.message,
+            Some("Nothing is waiting for an authenticator code.".to_string())
+        );
+        assert!(state.awaiting_code.contains_key(&(130, alice)));
+    }
+
+    #[test]
+    fn trailing_code_commands() {
+        assert_eq!(
+            get_command("/cancel", 115, vec!["12", "123456"]),
+            Action::WithCode(115, "123456".to_string(), Box::new(Action::Cancel(115, 12)))
+        );
+        assert_eq!(
+            get_command("/confirm", 115, vec!["123456"]),
+            Action::WithCode(115, "123456".to_string(), Box::new(Action::Confirm(115)))
+        );
+        // A lone number is a transaction id, not a code.
+        assert_eq!(
+            get_command("/cancel", 115, vec!["123456"]),
+            Action::Cancel(115, 123_456)
+        );
+        assert_eq!(
+            get_command("123456", 115, vec![]),
+            Action::Code(115, "123456".to_string())
+        );
+    }
+
+    #[test]
+    fn value_moving_actions_wait_for_code() {
+        use crate::controller::types::State;
+
+        let mut state = State::default();
+        state.context.totp_secret = Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string());
+
+        let state = screen_reducer(&state, &Action::Cancel(116, 12));
+        assert_eq!(
+            state.message,
+            Some("Enter the 6-digit code from your authenticator app.".to_string())
+        );
+        assert_eq!(state.awaiting_code[&(116, None)].0, Action::Cancel(116, 12));
+
+        // Codes are only accepted for the chat that was asked.
+        let state = screen_reducer(&state, &Action::Code(117, "123456".to_string()));
+        assert_eq!(
+            state.message,
+            Some("Nothing is waiting for an authenticator code.".to_string())
+        );
+        assert!(state.awaiting_code.contains_key(&(116, None)));
+    }
+
+    #[test]
+    fn locked_session_only_allows_help() {
+        use crate::controller::types::State;
+
+        let mut state = State::default();
+        state.context.pin_hash =
+            Some("$argon2i$v=19$m=4096,t=3,p=1$c2FsdHNhbHQ$aGFzaA".to_string());
+
+        let locked = screen_reducer(&state, &Action::Balance(118));
+        assert_eq!(
+            locked.message,
+            Some("Grin Bot is locked. Unlock with /unlock followed by your PIN.".to_string())
+        );
+        let help = screen_reducer(&state, &Action::Help(118));
+        assert_ne!(help.message, locked.message);
+    }
+
+    #[test]
+    fn each_user_unlocks_their_own_session() {
+        use crate::controller::types::State;
+
+        let alice = Some("alice".to_string());
+        let bob = Some("bob".to_string());
+        let mut state = State::default();
+        state.context.pin_hash = Some(session::hash_pin("2468"));
+        state.context.lock_after = 10;
+
+        let state = screen_reducer(
+            &state,
+            &Action::Unlock(119, "2468".to_string()).sent_by(&alice),
+        );
+        assert!(!state.sessions[&alice].is_locked(Utc::now()));
+
+        // Someone else in the same chat is still locked out.
+        let locked = screen_reducer(&state, &Action::Home(119).sent_by(&bob));
+        assert_eq!(format!("{:?}", locked.screen), "Locked");
+        let home = screen_reducer(&state, &Action::Home(119).sent_by(&alice));
+        assert_eq!(format!("{:?}", home.screen), "Home");
+    }
+
+    #[test]
+    fn frozen_wallet_refuses_spending() {
+        use crate::controller::types::State;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = State::default();
+        state.context.data_dir = data_dir.path().to_str().unwrap().to_string();
+        freeze::freeze(&state.context.data_dir).unwrap();
+
+        let refused = screen_reducer(&state, &Action::Confirm(119));
+        assert!(refused
+            .message
+            .unwrap()
+            .starts_with("Grin Bot is frozen since"));
+
+        // Other commands still answer, after the notice.
+        let help = screen_reducer(&state, &Action::Help(119)).message.unwrap();
+        assert!(help.starts_with("Grin Bot is frozen since"));
+        assert!(help.len() > 200);
+    }
+
+    #[test]
+    fn role_specific_refusal() {
+        let users: Users = vec![
+            ("viewer1".to_string(), Role::Viewer),
+            ("spender1".to_string(), Role::Spender),
+        ]
+        .into_iter()
+        .collect();
+        let viewer = Some("viewer1".to_string());
+        let spender = Some("spender1".to_string());
+
+        assert_eq!(
+            get_action(120, &viewer, Some("/balance".to_string()), &users),
+            Action::Balance(120)
+        );
+        assert_eq!(
+            get_action(120, &viewer, Some("/cancel 12".to_string()), &users),
+            Action::NotPermitted(120, Role::Viewer, Role::Spender)
+        );
+        assert_eq!(
+            get_action(120, &spender, Some("/cancel 12".to_string()), &users),
+            Action::Cancel(120, 12)
+        );
+        assert_eq!(
+            get_action(
+                120,
+                &spender,
+                Some("/contact remove bob".to_string()),
+                &users
+            ),
+            Action::NotPermitted(120, Role::Spender, Role::Admin)
+        );
+    }
+
+    #[test]
+    fn read_only_refuses_changes() {
+        use crate::controller::types::State;
+
+        let mut state = State::default();
+        state.context.read_only = true;
+
+        let refused = screen_reducer(&state, &Action::Cancel(121, 12));
+        assert_eq!(
+            refused.message,
+            Some("Grin Bot is read-only. Only viewing commands are allowed.".to_string())
+        );
+        let help = screen_reducer(&state, &Action::Help(121));
+        assert_ne!(help.message, refused.message);
+    }
+
+    #[test]
+    fn backup_command() {
+        use crate::controller::types::State;
+        use crate::security::types::BackupQuiz;
+
+        let command = get_command("/backup", 99, vec!["ability", "absent"]);
+        assert_eq!(
+            command,
+            Action::Backup(99, vec!["ability".to_string(), "absent".to_string()])
+        );
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut state = State::default();
+        state.context.data_dir = data_dir.path().to_str().unwrap().to_string();
+        state.backup_quiz = Some(BackupQuiz {
+            words: vec!["abandon", "ability", "able", "about", "above", "absent"]
+                .into_iter()
+                .map(|w| w.to_string())
+                .collect(),
+            positions: vec![1, 2, 5],
+        });
+
+        let wrong = screen_reducer(&state, &get_command("/backup", 99, vec!["a", "b", "c"]));
+        assert!(wrong.backup_quiz.is_some());
+        let right = screen_reducer(
+            &wrong,
+            &get_command("/backup", 99, vec!["Ability", "able", "absent"]),
+        );
+        assert!(right.backup_quiz.is_none());
+        assert!(right.message.unwrap().starts_with("Backup verified."));
+    }
+
+    #[test]
+    fn create_command() {
+        assert_eq!(get_command("/create", 99, vec![]), Action::Create(99, None));
+        assert_eq!(
+            get_command("/create", 99, vec!["12"]),
+            Action::Create(99, Some(MnemonicLength::new(12).unwrap()))
+        );
+        assert_eq!(
+            get_command("/create", 99, vec!["13"]),
+            Action::CommandError(99, CommandParseError::MnemonicLengthParseError)
+        );
+    }
+
 }
@@ -1,6 +1,9 @@
 //! Main controller for the app
 //!
 
+/// Pending sends waiting for /confirm.
+pub mod confirm;
+
 /// Reducer for calculating the next state from Actions.
 pub mod dispatch;
 
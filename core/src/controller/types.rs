@@ -1,8 +1,14 @@
+use chrono::{DateTime, Utc};
 use log::Level;
 use url::Url;
 
-use crate::service::types::GrinAmount;
+use crate::contacts::book;
+use crate::contacts::types::{Recipient, ResolvedRecipient};
+use crate::monitor::types::{AlertDirection, DigestSchedule, Frequency};
+use crate::security::types::{BackupQuiz, Role, Session};
+use crate::service::types::{GrinAmount, MnemonicLength};
 use crate::types::Context;
+use std::collections::BTreeMap;
 use std::fmt;
 
 /// Application state: which screen the user is on, previous screen, message to return.
@@ -14,6 +20,31 @@ pub struct State {
     pub message: Option<String>,
     pub context: Context,
     pub error_level: Option<Level>,
+    /// Rows of commands offered as buttons with the message.
+    pub buttons: Vec<Vec<String>>,
+    /// Chat user who sent the action being reduced. None for the command line and checks.
+    pub sender: Option<String>,
+    /// Sends waiting for /confirm, by chat id and the user who started them.
+    pub pending: BTreeMap<(i64, Option<String>), PendingSend>,
+    /// Actions waiting for an authenticator code, by chat id and the user who sent them,
+    /// with the time they were held.
+    pub awaiting_code: BTreeMap<(i64, Option<String>), (Action, DateTime<Utc>)>,
+    /// PIN lock state of each user, when a PIN is configured. The command line's is None.
+    pub sessions: BTreeMap<Option<String>, Session>,
+    /// Seconds before the reply must disappear from the chat. Set for replies with a secret.
+    pub expires: Option<u64>,
+    /// Recovery phrase of a wallet from /create, until /backup verifies it. Never stored.
+    pub backup_quiz: Option<BackupQuiz>,
+}
+
+/// A send shown to the user and waiting for /confirm or /abort.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingSend {
+    pub amount: GrinAmount,
+    pub recipient: ResolvedRecipient,
+    pub created: DateTime<Utc>,
+    /// An authenticator code was given with /send, so /confirm doesn't ask again.
+    pub verified: bool,
 }
 
 /// State that can be logged.
@@ -41,9 +72,20 @@ impl LoggableState {
 pub enum Screen {
     Home,
     Create,
+    Backup,
     Send,
     Balance,
+    Status,
+    Address,
     Help,
+    Repost,
+    Cancel,
+    Alerts,
+    Digest,
+    Schedule,
+    Contacts,
+    Confirm,
+    Locked,
 }
 
 impl Default for Screen {
@@ -53,42 +95,156 @@ impl Default for Screen {
 }
 
 /// Actions that modify the application state.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     Home(i64),
-    Create(i64),
-    Send(i64, GrinAmount, Url),
+    /// Recovery phrase length, or the one from config.yml.
+    Create(i64, Option<MnemonicLength>),
+    /// Words of the recovery phrase for the backup check. Empty to ask again.
+    Backup(i64, Vec<String>),
+    Send(i64, GrinAmount, Recipient),
+    Confirm(i64),
+    Abort(i64),
     Balance(i64),
+    Status(i64),
+    Address(i64),
+    Repost(i64, u32),
+    Cancel(i64, u32),
+    AddAlert(i64, AlertDirection, f64),
+    RemoveAlert(i64, usize),
+    Alerts(i64),
+    Digest(i64),
+    SetDigest(i64, Option<DigestSchedule>),
+    AddSchedule(i64, GrinAmount, Recipient, Frequency),
+    RemoveSchedule(i64, usize),
+    ApproveSchedule(i64, usize),
+    SkipSchedule(i64, usize),
+    Schedules(i64),
+    AddContact(i64, String, Url, Option<String>),
+    RemoveContact(i64, String),
+    Contacts(i64),
     Help(i64),
     NoUsername(i64),
     WrongUsername(i64),
+    /// A known user without the role an action needs: (id, user's role, required role).
+    NotPermitted(i64, Role, Role),
     ModeNotSupported(i64),
     Back(i64),
     CommandError(i64, CommandParseError),
     Unknown(i64),
+    Tick(i64),
+    /// An action with a trailing authenticator code, e.g. /cancel 12 123456.
+    WithCode(i64, String, Box<Action>),
+    /// An authenticator code sent on its own after a prompt.
+    Code(i64, String),
+    Unlock(i64, String),
+    Lock(i64),
+    Freeze(i64),
+    /// An action with the chat user who sent it. Only that user may confirm, abort or
+    /// give a code for what it starts.
+    SentBy(String, Box<Action>),
+}
+
+impl Action {
+    /// Chat the action came from.
+    pub fn id(&self) -> i64 {
+        use Action::*;
+        match self {
+            Home(id)
+            | Create(id, _)
+            | Backup(id, _)
+            | Send(id, ..)
+            | Confirm(id)
+            | Abort(id)
+            | Balance(id)
+            | Status(id)
+            | Address(id)
+            | Repost(id, _)
+            | Cancel(id, _)
+            | AddAlert(id, ..)
+            | RemoveAlert(id, _)
+            | Alerts(id)
+            | Digest(id)
+            | SetDigest(id, _)
+            | AddSchedule(id, ..)
+            | RemoveSchedule(id, _)
+            | ApproveSchedule(id, _)
+            | SkipSchedule(id, _)
+            | Schedules(id)
+            | AddContact(id, ..)
+            | RemoveContact(id, _)
+            | Contacts(id)
+            | Help(id)
+            | NoUsername(id)
+            | WrongUsername(id)
+            | NotPermitted(id, ..)
+            | ModeNotSupported(id)
+            | Back(id)
+            | CommandError(id, _)
+            | Unknown(id)
+            | Tick(id)
+            | WithCode(id, ..)
+            | Code(id, _)
+            | Unlock(id, _)
+            | Lock(id)
+            | Freeze(id) => *id,
+            SentBy(_, inner) => inner.id(),
+        }
+    }
+
+    /// The action, marked as sent by a chat user when there is one.
+    pub fn sent_by(self, user: &Option<String>) -> Self {
+        match user {
+            Some(user) => Action::SentBy(user.clone(), Box::new(self)),
+            None => self,
+        }
+    }
+
+    /// Whether the reply carries a secret, like a seed, and so must only go to a private chat.
+    pub fn reveals_secret(&self) -> bool {
+        match self {
+            Action::Create(..) | Action::Backup(..) => true,
+            Action::WithCode(_, _, inner) | Action::SentBy(_, inner) => inner.reveals_secret(),
+            _ => false,
+        }
+    }
+
+    /// Whether the user answers with a secret, like words of the recovery phrase,
+    /// and so must only run the command in a private chat.
+    pub fn asks_secret(&self) -> bool {
+        match self {
+            Action::Backup(..) => true,
+            Action::WithCode(_, _, inner) | Action::SentBy(_, inner) => inner.asks_secret(),
+            _ => false,
+        }
+    }
 }
 
 /// A parsed send command.
 #[derive(Default, Clone)]
 pub struct SendCommand {
     pub amount: f64,
-    pub destination: Option<Url>,
+    pub destination: Option<Recipient>,
 }
 
 impl SendCommand {
     pub fn usage() -> String {
-        "Wrong number of arguments.\n\nUsage: <pre>/send 0.001 http://some-recipient123.org</pre>"
+        "Wrong number of arguments.\n\nUsage: <pre>/send 0.001 http://some-recipient123.org</pre> <pre>/send 0.001 alice</pre>"
             .to_string()
     }
 
-    /// Convert string tokens of user command parameters to valid Url and float.
+    /// Convert string tokens of user command parameters to a valid recipient and float.
+    /// The recipient is a Url or the name of a contact.
     pub fn parse(command: Vec<&str>) -> Result<Self, CommandParseError> {
         use CommandParseError::*;
         if command.len() != 2 {
             return Err(WrongNumberOfArgsError(SendCommand::usage()));
         } else {
-            let url = match Url::parse(command[1]) {
-                Ok(url) => url,
+            let recipient = match Url::parse(command[1]) {
+                Ok(url) => Recipient::Url(url),
+                Err(_) if book::is_valid_name(command[1]) => {
+                    Recipient::Name(command[1].to_string())
+                }
                 Err(_) => return Err(UrlParseError),
             };
             let amount = match command[0].parse::<f64>() {
@@ -97,18 +253,209 @@ impl SendCommand {
             };
             Ok(SendCommand {
                 amount,
-                destination: Some(url),
+                destination: Some(recipient),
             })
         }
     }
 }
 
+/// A parsed command that takes a transaction id, e.g. /cancel 12.
+#[derive(Default, Clone)]
+pub struct TxCommand {
+    pub tx_id: u32,
+}
+
+impl TxCommand {
+    pub fn usage(command_type: &str) -> String {
+        format!(
+            "Wrong number of arguments.\n\nUsage: <pre>{} 12</pre>",
+            command_type
+        )
+    }
+
+    /// Convert string tokens of user command parameters to a transaction id.
+    pub fn parse(command_type: &str, command: Vec<&str>) -> Result<Self, CommandParseError> {
+        use CommandParseError::*;
+        if command.len() != 1 {
+            return Err(WrongNumberOfArgsError(TxCommand::usage(command_type)));
+        }
+        match command[0].parse::<u32>() {
+            Ok(tx_id) => Ok(TxCommand { tx_id }),
+            Err(_) => Err(TxIdParseError),
+        }
+    }
+}
+
+/// A parsed alert command: /alert below 5, /alert above 100 or /alert remove 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertCommand {
+    Add(AlertDirection, f64),
+    Remove(usize),
+}
+
+impl AlertCommand {
+    pub fn usage() -> String {
+        "Wrong number of arguments.\n\nUsage: <pre>/alert below 5</pre> <pre>/alert above 100</pre> <pre>/alert remove 1</pre>"
+            .to_string()
+    }
+
+    /// Convert string tokens of user command parameters to an alert rule or removal.
+    pub fn parse(command: Vec<&str>) -> Result<Self, CommandParseError> {
+        use CommandParseError::*;
+        if command.len() != 2 {
+            return Err(WrongNumberOfArgsError(AlertCommand::usage()));
+        }
+        let direction = match command[0] {
+            "below" => AlertDirection::Below,
+            "above" => AlertDirection::Above,
+            "remove" => {
+                return match command[1].parse::<usize>() {
+                    Ok(number) => Ok(AlertCommand::Remove(number)),
+                    Err(_) => Err(AlertNumberParseError),
+                }
+            }
+            _ => return Err(WrongNumberOfArgsError(AlertCommand::usage())),
+        };
+        match command[1].parse::<f64>() {
+            Ok(threshold) if threshold >= 0.0 => Ok(AlertCommand::Add(direction, threshold)),
+            _ => Err(AmountParseError),
+        }
+    }
+}
+
+/// A parsed digest command: /digest, /digest daily 09:00 or /digest off.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DigestCommand {
+    Now,
+    Set(Option<DigestSchedule>),
+}
+
+impl DigestCommand {
+    pub fn usage() -> String {
+        "Wrong number of arguments.\n\nUsage: <pre>/digest daily 09:00</pre> <pre>/digest weekly 09:00</pre> <pre>/digest off</pre>"
+            .to_string()
+    }
+
+    /// Convert string tokens of user command parameters to a digest schedule.
+    pub fn parse(command: Vec<&str>) -> Result<Self, CommandParseError> {
+        use CommandParseError::*;
+        match command.len() {
+            0 => Ok(DigestCommand::Now),
+            1 if command[0] == "off" => Ok(DigestCommand::Set(None)),
+            2 => match DigestSchedule::parse(command[0], command[1]) {
+                Some(schedule) => Ok(DigestCommand::Set(Some(schedule))),
+                None => Err(ScheduleParseError),
+            },
+            _ => Err(WrongNumberOfArgsError(DigestCommand::usage())),
+        }
+    }
+}
+
+/// A parsed schedule command: /schedule send 2.5 https://alice.example monthly 1,
+/// /schedule list, /schedule remove 1, /schedule approve 1 or /schedule skip 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduleCommand {
+    Add(f64, Recipient, Frequency),
+    List,
+    Remove(usize),
+    Approve(usize),
+    Skip(usize),
+}
+
+impl ScheduleCommand {
+    pub fn usage() -> String {
+        "Wrong number of arguments.\n\nUsage: <pre>/schedule send 2.5 http://some-recipient123.org monthly 1</pre> <pre>/schedule send 1 http://some-recipient123.org weekly mon</pre> <pre>/schedule list</pre> <pre>/schedule remove 1</pre>"
+            .to_string()
+    }
+
+    /// Convert string tokens of user command parameters to a scheduled payment or change.
+    pub fn parse(command: Vec<&str>) -> Result<Self, CommandParseError> {
+        use CommandParseError::*;
+        match command.split_first() {
+            None => Ok(ScheduleCommand::List),
+            Some((&"list", [])) => Ok(ScheduleCommand::List),
+            Some((&"send", args)) if args.len() > 2 => {
+                let send = SendCommand::parse(args[..2].to_vec())?;
+                match Frequency::parse(&args[2..]) {
+                    Some(frequency) => Ok(ScheduleCommand::Add(
+                        send.amount,
+                        send.destination.unwrap(),
+                        frequency,
+                    )),
+                    None => Err(ScheduleParseError),
+                }
+            }
+            Some((&change, [number])) => {
+                let number = match number.parse::<usize>() {
+                    Ok(number) => number,
+                    Err(_) => return Err(ScheduleNumberParseError),
+                };
+                match change {
+                    "remove" => Ok(ScheduleCommand::Remove(number)),
+                    "approve" => Ok(ScheduleCommand::Approve(number)),
+                    "skip" => Ok(ScheduleCommand::Skip(number)),
+                    _ => Err(WrongNumberOfArgsError(ScheduleCommand::usage())),
+                }
+            }
+            _ => Err(WrongNumberOfArgsError(ScheduleCommand::usage())),
+        }
+    }
+}
+
+/// A parsed contact command: /contact add alice http://some-recipient123.org or
+/// /contact remove alice.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContactCommand {
+    Add(String, Url, Option<String>),
+    Remove(String),
+}
+
+impl ContactCommand {
+    pub fn usage() -> String {
+        "Wrong number of arguments.\n\nUsage: <pre>/contact add alice http://some-recipient123.org</pre> <pre>/contact add alice http://some-recipient123.org proof_address</pre> <pre>/contact remove alice</pre>"
+            .to_string()
+    }
+
+    /// Convert string tokens of user command parameters to a contact or removal.
+    pub fn parse(command: Vec<&str>) -> Result<Self, CommandParseError> {
+        use CommandParseError::*;
+        match command.as_slice() {
+            ["add", name, url] | ["add", name, url, _] => {
+                if !book::is_valid_name(name) {
+                    return Err(ContactNameParseError);
+                }
+                let url = match Url::parse(url) {
+                    Ok(url) => url,
+                    Err(_) => return Err(UrlParseError),
+                };
+                let proof_address = match command.get(3) {
+                    Some(address) if book::is_valid_proof_address(address) => {
+                        Some(address.to_string())
+                    }
+                    Some(_) => return Err(ProofAddressParseError),
+                    None => None,
+                };
+                Ok(ContactCommand::Add(name.to_string(), url, proof_address))
+            }
+            ["remove", name] => Ok(ContactCommand::Remove(name.to_string())),
+            _ => Err(WrongNumberOfArgsError(ContactCommand::usage())),
+        }
+    }
+}
+
 /// Errors associated with parsing commands.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CommandParseError {
     WrongNumberOfArgsError(String),
     UrlParseError,
     AmountParseError,
+    TxIdParseError,
+    AlertNumberParseError,
+    ScheduleParseError,
+    ScheduleNumberParseError,
+    ContactNameParseError,
+    ProofAddressParseError,
+    MnemonicLengthParseError,
 }
 
 impl fmt::Display for CommandParseError {
@@ -0,0 +1,7 @@
+//! Health checks of the bot and the services it depends on
+//!
+
+/// The /status report.
+pub mod status;
+/// Check results.
+pub mod types;
@@ -0,0 +1,306 @@
+use askama::Template;
+use chrono::{DateTime, Duration, Utc};
+use grin_wallet_libwallet::TxLogEntryType;
+
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::diagnostics::types::{Check, CheckLevel};
+use crate::monitor::stuck;
+use crate::service::grin;
+use crate::template::templates::StatusTemplate;
+use crate::types::Context;
+
+/// Owner API replies slower than this are flagged.
+const SLOW_MS: u128 = 1000;
+
+/// Blocks the wallet may trail the node before it's flagged.
+const MAX_BEHIND: u64 = 1;
+
+/// Runs every check and renders the report.
+pub fn report(context: &Context) -> String {
+    StatusTemplate {
+        checks: &run(context, Utc::now()),
+    }
+    .render()
+    .unwrap()
+}
+
+/// Runs every check, in the order they're reported.
+pub fn run(context: &Context, now: DateTime<Utc>) -> Vec<Check> {
+    // Before this report's own wallet calls update it.
+    let last_success = grin::last_success();
+
+    let mut checks = vec![
+        Check::new(
+            "Grin Bot",
+            CheckLevel::Ok,
+            format!(
+                "version {}, up {}",
+                env!("CARGO_PKG_VERSION"),
+                duration(now - context.started)
+            ),
+        ),
+        Check::new("Transport", CheckLevel::Ok, context.transport.to_string()),
+        api_secret(&context.wallet_dir),
+    ];
+
+    let (check, node_height) = owner_api(context);
+    checks.push(check);
+    match node_height {
+        Some((node_height, updated)) => {
+            checks.append(&mut wallet_checks(context, node_height, updated));
+        }
+        None => {
+            for name in &["Heights", "Pending confirmations"] {
+                checks.push(Check::new(
+                    name,
+                    CheckLevel::Failed,
+                    "unknown without the owner API".to_string(),
+                ));
+            }
+        }
+    }
+
+    checks.push(last_call(last_success, now, context.watch_interval));
+    checks
+}
+
+/// Whether .api_secret is in the wallet directory. Never shows its content.
+pub fn api_secret(wallet_dir: &str) -> Check {
+    let path = Path::new(wallet_dir).join(".api_secret");
+    match fs::read_to_string(&path) {
+        Ok(secret) if !secret.trim().is_empty() => Check::new(
+            "API secret",
+            CheckLevel::Ok,
+            format!("present in {}", wallet_dir),
+        ),
+        Ok(_) => Check::new(
+            "API secret",
+            CheckLevel::Failed,
+            format!("{} is empty", path.display()),
+        ),
+        Err(e) => Check::new(
+            "API secret",
+            CheckLevel::Failed,
+            format!("can't read {}: {}", path.display(), e),
+        ),
+    }
+}
+
+/// Whether the owner API answers, and how fast. Also returns the node height
+/// it reported, and whether the wallet could reach the node for it.
+pub fn owner_api(context: &Context) -> (Check, Option<(u64, bool)>) {
+    let start = Instant::now();
+    let node_height = grin::node_height(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    );
+    let latency = start.elapsed().as_millis();
+    match node_height {
+        Ok(height) if latency > SLOW_MS => (
+            Check::new(
+                "Owner API",
+                CheckLevel::Warning,
+                format!("slow, {} ms at {}", latency, context.owner_endpoint),
+            ),
+            Some(height),
+        ),
+        Ok(height) => (
+            Check::new(
+                "Owner API",
+                CheckLevel::Ok,
+                format!("{} ms at {}", latency, context.owner_endpoint),
+            ),
+            Some(height),
+        ),
+        Err(e) => (
+            Check::new(
+                "Owner API",
+                CheckLevel::Failed,
+                format!("unreachable at {}: {}", context.owner_endpoint, e),
+            ),
+            None,
+        ),
+    }
+}
+
+/// Height and pending transaction checks, once the owner API answers.
+fn wallet_checks(context: &Context, node_height: u64, updated: bool) -> Vec<Check> {
+    let info = match grin::wallet_info(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    ) {
+        Ok(info) => info,
+        Err(e) => {
+            let failed = |name| Check::new(name, CheckLevel::Failed, format!("{}", e));
+            return vec![failed("Heights"), failed("Pending confirmations")];
+        }
+    };
+    let pending = match grin::retrieve_txs(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    ) {
+        Ok(txs) => {
+            let unconfirmed = txs
+                .iter()
+                .filter(|tx| {
+                    !tx.confirmed
+                        && (tx.tx_type == TxLogEntryType::TxSent
+                            || tx.tx_type == TxLogEntryType::TxReceived)
+                })
+                .count();
+            let stuck = stuck::stuck_txs(
+                &txs,
+                info.last_confirmed_height,
+                Utc::now(),
+                context.stuck_tx_blocks,
+            )
+            .len();
+            pending_check(unconfirmed, stuck, context.stuck_tx_blocks)
+        }
+        Err(e) => Check::new("Pending confirmations", CheckLevel::Failed, e.to_string()),
+    };
+    vec![
+        height_check(node_height, updated, info.last_confirmed_height),
+        pending,
+    ]
+}
+
+/// Node height versus the wallet's last confirmed height.
+pub fn height_check(node_height: u64, updated: bool, wallet_height: u64) -> Check {
+    let behind = node_height.saturating_sub(wallet_height);
+    if !updated {
+        Check::new(
+            "Heights",
+            CheckLevel::Warning,
+            format!(
+                "the wallet can't reach the node, last known node height {}",
+                node_height
+            ),
+        )
+    } else if behind > MAX_BEHIND {
+        Check::new(
+            "Heights",
+            CheckLevel::Warning,
+            format!(
+                "wallet {} is {} blocks behind node {}",
+                wallet_height, behind, node_height
+            ),
+        )
+    } else {
+        Check::new(
+            "Heights",
+            CheckLevel::Ok,
+            format!("node {}, wallet {}", node_height, wallet_height),
+        )
+    }
+}
+
+/// Unconfirmed transactions, flagged when some are stuck.
+pub fn pending_check(unconfirmed: usize, stuck: usize, stuck_tx_blocks: u64) -> Check {
+    match (unconfirmed, stuck) {
+        (0, _) => Check::new("Pending confirmations", CheckLevel::Ok, "none".to_string()),
+        (n, 0) => Check::new(
+            "Pending confirmations",
+            CheckLevel::Ok,
+            format!("{} unconfirmed", n),
+        ),
+        (n, s) => Check::new(
+            "Pending confirmations",
+            CheckLevel::Warning,
+            format!(
+                "{} unconfirmed, {} for more than {} blocks",
+                n, s, stuck_tx_blocks
+            ),
+        ),
+    }
+}
+
+/// Time of the last successful wallet call. The periodic checks make one
+/// every watch interval, so an old one means the wallet has been failing.
+pub fn last_call(last: Option<DateTime<Utc>>, now: DateTime<Utc>, watch_interval: u64) -> Check {
+    match last {
+        None => Check::new(
+            "Last wallet call",
+            CheckLevel::Warning,
+            "none succeeded since the bot started".to_string(),
+        ),
+        Some(last) => {
+            let age = now - last;
+            let detail = format!(
+                "{} ({} ago)",
+                last.format("%Y-%m-%d %H:%M:%S UTC"),
+                duration(age)
+            );
+            if age > Duration::seconds(2 * watch_interval as i64 + 60) {
+                Check::new("Last wallet call", CheckLevel::Warning, detail)
+            } else {
+                Check::new("Last wallet call", CheckLevel::Ok, detail)
+            }
+        }
+    }
+}
+
+/// A duration like 2d 3h 4m, or 12s under a minute.
+fn duration(d: Duration) -> String {
+    let secs = d.num_seconds().max(0);
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", secs),
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, m) => format!("{}d {}h {}m", d, h, m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heights() {
+        assert_eq!(height_check(100, true, 100).level, CheckLevel::Ok);
+        assert_eq!(height_check(100, true, 99).level, CheckLevel::Ok);
+        let behind = height_check(100, true, 90);
+        assert_eq!(behind.level, CheckLevel::Warning);
+        assert_eq!(behind.detail, "wallet 90 is 10 blocks behind node 100");
+        assert_eq!(height_check(100, false, 100).level, CheckLevel::Warning);
+    }
+
+    #[test]
+    fn last_wallet_call() {
+        let now = Utc::now();
+        assert_eq!(last_call(None, now, 60).level, CheckLevel::Warning);
+        assert_eq!(
+            last_call(Some(now - Duration::seconds(90)), now, 60).level,
+            CheckLevel::Ok
+        );
+        let old = last_call(Some(now - Duration::minutes(75)), now, 60);
+        assert_eq!(old.level, CheckLevel::Warning);
+        assert!(old.detail.ends_with("(1h 15m ago)"));
+    }
+
+    #[test]
+    fn api_secret_is_not_shown() {
+        let dir = tempfile::tempdir().unwrap();
+        let wallet_dir = dir.path().to_str().unwrap();
+        assert_eq!(api_secret(wallet_dir).level, CheckLevel::Failed);
+
+        fs::write(dir.path().join(".api_secret"), "s3cr3t").unwrap();
+        let check = api_secret(wallet_dir);
+        assert_eq!(check.level, CheckLevel::Ok);
+        assert!(!check.detail.contains("s3cr3t"));
+    }
+
+    #[test]
+    fn pending() {
+        assert_eq!(pending_check(0, 0, 60).detail, "none");
+        assert_eq!(pending_check(2, 0, 60).level, CheckLevel::Ok);
+        assert_eq!(pending_check(2, 1, 60).level, CheckLevel::Warning);
+    }
+}
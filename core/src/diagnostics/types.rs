@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// How a check went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckLevel {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl fmt::Display for CheckLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckLevel::Ok => write!(f, "OK"),
+            CheckLevel::Warning => write!(f, "Warning"),
+            CheckLevel::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+/// The result of one check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub level: CheckLevel,
+    pub detail: String,
+    /// How to fix a failed check.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    pub fn new(name: &str, level: CheckLevel, detail: String) -> Self {
+        Check {
+            name: name.to_string(),
+            level,
+            detail,
+            hint: None,
+        }
+    }
+
+    /// Adds how to fix the problem, unless the check passed.
+    pub fn with_hint(self, hint: &str) -> Self {
+        match self.level {
+            CheckLevel::Ok => self,
+            _ => Check {
+                hint: Some(hint.to_string()),
+                ..self
+            },
+        }
+    }
+}
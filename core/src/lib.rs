@@ -1,5 +1,17 @@
+/// Address book of named recipients
+pub mod contacts;
 pub mod controller;
+/// Health checks for /status
+pub mod diagnostics;
+/// Periodic wallet checks
+pub mod monitor;
+/// Spending safeguards
+pub mod security;
 pub mod service;
+/// Persistent bot data
+pub mod storage;
+/// Child processes kept running with the bot
+pub mod supervisor;
 pub mod template;
 /// Core types
 pub mod types;
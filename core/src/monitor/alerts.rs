@@ -0,0 +1,159 @@
+use askama::Template;
+
+use std::error::Error;
+
+use crate::monitor::types::{Alert, AlertDirection, Alerts, Notice};
+use crate::service::grin;
+use crate::storage;
+use crate::template::templates::{AlertsTemplate, BalanceAlertTemplate};
+use crate::types::Context;
+
+/// Data directory file with the user's balance alerts.
+const ALERTS_FILE: &str = "alerts.json";
+
+/// Fraction of the threshold the balance must move back past before
+/// a triggered alert can fire again.
+const HYSTERESIS: f64 = 0.05;
+
+/// Returns a notice for each alert whose threshold was crossed since the last check.
+pub fn check(context: &Context) -> Result<Vec<Notice>, Box<dyn Error>> {
+    let mut alerts: Alerts = storage::load(&context.data_dir, ALERTS_FILE)?;
+    if alerts.alerts.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let info = grin::wallet_info(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+    let spendable = info.amount_currently_spendable;
+    let fired = evaluate(&mut alerts.alerts, spendable);
+    storage::save(&context.data_dir, ALERTS_FILE, &alerts)?;
+
+    Ok(fired
+        .iter()
+        .map(|alert| {
+            let message = BalanceAlertTemplate {
+                direction: alert.direction,
+                threshold: alert.threshold,
+                spendable,
+            }
+            .render()
+            .unwrap();
+            Notice::new(message)
+        })
+        .collect())
+}
+
+/// Updates each alert for the current balance and returns the ones that fired.
+pub fn evaluate(alerts: &mut [Alert], spendable: f64) -> Vec<Alert> {
+    let mut fired = vec![];
+    for alert in alerts.iter_mut() {
+        let margin = alert.threshold * HYSTERESIS;
+        let (crossed, rearmed) = match alert.direction {
+            AlertDirection::Below => (
+                spendable < alert.threshold,
+                spendable >= alert.threshold + margin,
+            ),
+            AlertDirection::Above => (
+                spendable > alert.threshold,
+                spendable <= alert.threshold - margin,
+            ),
+        };
+        if crossed && !alert.triggered {
+            alert.triggered = true;
+            fired.push(alert.clone());
+        } else if rearmed {
+            alert.triggered = false;
+        }
+    }
+    fired
+}
+
+/// Adds an alert and returns the updated list.
+pub fn add(
+    data_dir: &str,
+    direction: AlertDirection,
+    threshold: f64,
+) -> Result<String, Box<dyn Error>> {
+    let mut alerts: Alerts = storage::load(data_dir, ALERTS_FILE)?;
+    alerts.alerts.push(Alert {
+        direction,
+        threshold,
+        triggered: false,
+    });
+    storage::save(data_dir, ALERTS_FILE, &alerts)?;
+    Ok(render_list(&alerts))
+}
+
+/// Removes an alert by its number in the list (starting at 1) and returns the updated list.
+pub fn remove(data_dir: &str, number: usize) -> Result<String, Box<dyn Error>> {
+    let mut alerts: Alerts = storage::load(data_dir, ALERTS_FILE)?;
+    if number == 0 || number > alerts.alerts.len() {
+        return Ok(format!(
+            "There is no alert {}.\n\n{}",
+            number,
+            render_list(&alerts)
+        ));
+    }
+    alerts.alerts.remove(number - 1);
+    storage::save(data_dir, ALERTS_FILE, &alerts)?;
+    Ok(render_list(&alerts))
+}
+
+/// Lists the alerts.
+pub fn list(data_dir: &str) -> Result<String, Box<dyn Error>> {
+    let alerts: Alerts = storage::load(data_dir, ALERTS_FILE)?;
+    Ok(render_list(&alerts))
+}
+
+fn render_list(alerts: &Alerts) -> String {
+    AlertsTemplate {
+        alerts: &alerts.alerts,
+    }
+    .render()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(direction: AlertDirection, threshold: f64) -> Alert {
+        Alert {
+            direction,
+            threshold,
+            triggered: false,
+        }
+    }
+
+    #[test]
+    fn below_fires_once() {
+        let mut alerts = vec![alert(AlertDirection::Below, 5.0)];
+        assert_eq!(evaluate(&mut alerts, 4.0).len(), 1);
+        assert_eq!(evaluate(&mut alerts, 3.0).len(), 0);
+        // Back above the threshold, but inside the hysteresis margin.
+        assert_eq!(evaluate(&mut alerts, 5.1).len(), 0);
+        assert_eq!(evaluate(&mut alerts, 4.9).len(), 0);
+    }
+
+    #[test]
+    fn below_rearms_past_margin() {
+        let mut alerts = vec![alert(AlertDirection::Below, 5.0)];
+        assert_eq!(evaluate(&mut alerts, 4.0).len(), 1);
+        assert_eq!(evaluate(&mut alerts, 6.0).len(), 0);
+        assert_eq!(evaluate(&mut alerts, 4.0).len(), 1);
+    }
+
+    #[test]
+    fn above_fires_once() {
+        let mut alerts = vec![alert(AlertDirection::Above, 100.0)];
+        assert_eq!(evaluate(&mut alerts, 50.0).len(), 0);
+        assert_eq!(evaluate(&mut alerts, 101.0).len(), 1);
+        assert_eq!(evaluate(&mut alerts, 99.0).len(), 0);
+        assert_eq!(evaluate(&mut alerts, 101.0).len(), 0);
+        assert_eq!(evaluate(&mut alerts, 90.0).len(), 0);
+        assert_eq!(evaluate(&mut alerts, 101.0).len(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+use askama::Template;
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use grin_wallet_libwallet::{TxLogEntry, TxLogEntryType};
+
+use std::error::Error;
+
+use crate::monitor::types::{DigestPeriod, DigestSchedule, DigestState, DigestTotals, Notice};
+use crate::service::grin;
+use crate::service::types::NanoGrinAmount;
+use crate::storage;
+use crate::template::templates::DigestTemplate;
+use crate::types::Context;
+
+/// Data directory file with the digest schedule and last send time.
+const DIGEST_FILE: &str = "digest.json";
+
+/// Returns the digest if one is due.
+pub fn check(context: &Context) -> Result<Vec<Notice>, Box<dyn Error>> {
+    let mut state: DigestState = storage::load(&context.data_dir, DIGEST_FILE)?;
+    let schedule = match current_schedule(context, &state) {
+        Some(schedule) => schedule,
+        None => return Ok(vec![]),
+    };
+
+    let now = Utc::now();
+    let slot = last_slot(&schedule, Local::now()).with_timezone(&Utc);
+    let start = match state.last_sent {
+        Some(last_sent) if last_sent >= slot => return Ok(vec![]),
+        Some(last_sent) => last_sent,
+        None => {
+            // First run with this schedule. Start counting from the last slot.
+            state.last_sent = Some(slot);
+            storage::save(&context.data_dir, DIGEST_FILE, &state)?;
+            return Ok(vec![]);
+        }
+    };
+
+    let message = render(context, schedule.period, start, now)?;
+    state.last_sent = Some(now);
+    storage::save(&context.data_dir, DIGEST_FILE, &state)?;
+    Ok(vec![Notice::new(message)])
+}
+
+/// Sets or clears the digest schedule and describes it.
+pub fn set_schedule(
+    data_dir: &str,
+    schedule: Option<DigestSchedule>,
+) -> Result<String, Box<dyn Error>> {
+    let mut state: DigestState = storage::load(data_dir, DIGEST_FILE)?;
+    state.schedule = schedule;
+    state.overridden = true;
+    // Start counting from now rather than sending for a slot that has already passed.
+    state.last_sent = Some(Utc::now());
+    storage::save(data_dir, DIGEST_FILE, &state)?;
+    Ok(match schedule {
+        Some(schedule) => format!("Digest will be sent {}.", schedule),
+        None => "Digest turned off.".to_string(),
+    })
+}
+
+/// Renders a digest covering the last period now, without changing the schedule.
+pub fn now(context: &Context) -> Result<String, Box<dyn Error>> {
+    let state: DigestState = storage::load(&context.data_dir, DIGEST_FILE)?;
+    let (period, schedule_text) = match current_schedule(context, &state) {
+        Some(schedule) => (schedule.period, format!("Sent {}.", schedule)),
+        None => (
+            DigestPeriod::Daily,
+            "No schedule. Set one with <pre>/digest daily 09:00</pre>".to_string(),
+        ),
+    };
+    let end = Utc::now();
+    let message = render(context, period, end - period_length(period), end)?;
+    Ok(format!("{}\n\n{}", message, schedule_text))
+}
+
+/// The schedule set from chat, or the one in config.yml.
+fn current_schedule(context: &Context, state: &DigestState) -> Option<DigestSchedule> {
+    if state.overridden {
+        state.schedule
+    } else {
+        context.digest
+    }
+}
+
+fn period_length(period: DigestPeriod) -> Duration {
+    match period {
+        DigestPeriod::Daily => Duration::days(1),
+        DigestPeriod::Weekly => Duration::weeks(1),
+    }
+}
+
+/// The most recent scheduled time at or before `now`.
+pub fn last_slot<Tz: TimeZone>(schedule: &DigestSchedule, now: DateTime<Tz>) -> DateTime<Tz> {
+    let days_back = match schedule.period {
+        DigestPeriod::Daily => 0,
+        DigestPeriod::Weekly => now.weekday().num_days_from_monday() as i64,
+    };
+    let day = now.date() - Duration::days(days_back);
+    let slot = day
+        .and_hms_opt(schedule.hour, schedule.minute, 0)
+        .unwrap_or_else(|| now.clone());
+    if slot > now {
+        slot - period_length(schedule.period)
+    } else {
+        slot
+    }
+}
+
+/// Adds up transactions created between `start` and `end`. Cancelled transactions are left out.
+pub fn summarize(txs: &[TxLogEntry], start: DateTime<Utc>, end: DateTime<Utc>) -> DigestTotals {
+    let mut totals = DigestTotals::default();
+    for tx in txs
+        .iter()
+        .filter(|tx| tx.creation_ts >= start && tx.creation_ts < end)
+    {
+        match tx.tx_type {
+            TxLogEntryType::TxReceived | TxLogEntryType::ConfirmedCoinbase => {
+                totals.received_count += 1;
+                totals.received += tx.amount_credited;
+            }
+            TxLogEntryType::TxSent => {
+                let fee = tx.fee.unwrap_or(0);
+                totals.sent_count += 1;
+                totals.sent += tx
+                    .amount_debited
+                    .saturating_sub(tx.amount_credited)
+                    .saturating_sub(fee);
+                totals.fees += fee;
+            }
+            _ => continue,
+        }
+        totals.net += tx.amount_credited as i64 - tx.amount_debited as i64;
+    }
+    totals
+}
+
+fn render(
+    context: &Context,
+    period: DigestPeriod,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<String, Box<dyn Error>> {
+    let info = grin::wallet_info(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+    let txs = grin::retrieve_txs(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+    let totals = summarize(&txs, start, end);
+    let grin = |nano: u64| NanoGrinAmount::new(nano as f64).as_grin();
+
+    Ok(DigestTemplate {
+        title: match period {
+            DigestPeriod::Daily => "Daily digest",
+            DigestPeriod::Weekly => "Weekly digest",
+        },
+        start: &start
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        end: &end
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        opening: info.total - NanoGrinAmount::new(totals.net as f64).as_grin(),
+        closing: info.total,
+        received: grin(totals.received),
+        received_count: totals.received_count,
+        sent: grin(totals.sent),
+        sent_count: totals.sent_count,
+        fees: grin(totals.fees),
+        pending: info.amount_awaiting_confirmation + info.amount_awaiting_finalization,
+        locked: info.amount_locked,
+    }
+    .render()
+    .unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(tx_type: &str, created: &str, credited: u64, debited: u64, fee: u64) -> TxLogEntry {
+        let json = format!(
+            r#"{{
+              "parent_key_id": "0200000000000000000000000000000000",
+              "id": 1,
+              "tx_slate_id": null,
+              "tx_type": "{}",
+              "creation_ts": "{}",
+              "confirmation_ts": null,
+              "confirmed": true,
+              "num_inputs": 1,
+              "num_outputs": 1,
+              "amount_credited": {},
+              "amount_debited": {},
+              "fee": {},
+              "ttl_cutoff_height": null,
+              "messages": null,
+              "stored_tx": null,
+              "kernel_excess": null,
+              "kernel_lookup_min_height": null,
+              "payment_proof": null
+            }}"#,
+            tx_type, created, credited, debited, fee
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn time(t: &str) -> DateTime<Utc> {
+        t.parse().unwrap()
+    }
+
+    #[test]
+    fn daily_slot() {
+        let schedule = DigestSchedule::parse("daily", "09:00").unwrap();
+        assert_eq!(
+            last_slot(&schedule, time("2019-11-06T10:00:00Z")),
+            time("2019-11-06T09:00:00Z")
+        );
+        assert_eq!(
+            last_slot(&schedule, time("2019-11-06T08:00:00Z")),
+            time("2019-11-05T09:00:00Z")
+        );
+    }
+
+    #[test]
+    fn weekly_slot() {
+        // 2019-11-06 is a Wednesday.
+        let schedule = DigestSchedule::parse("weekly", "09:00").unwrap();
+        assert_eq!(
+            last_slot(&schedule, time("2019-11-06T10:00:00Z")),
+            time("2019-11-04T09:00:00Z")
+        );
+        assert_eq!(
+            last_slot(&schedule, time("2019-11-04T08:00:00Z")),
+            time("2019-10-28T09:00:00Z")
+        );
+    }
+
+    #[test]
+    fn bad_schedule() {
+        assert_eq!(DigestSchedule::parse("hourly", "09:00"), None);
+        assert_eq!(DigestSchedule::parse("daily", "25:00"), None);
+        assert_eq!(DigestSchedule::parse("daily", "9"), None);
+    }
+
+    #[test]
+    fn totals_in_period() {
+        let txs = vec![
+            tx("TxReceived", "2019-11-06T01:00:00Z", 2_000_000_000, 0, 0),
+            tx(
+                "TxSent",
+                "2019-11-06T02:00:00Z",
+                500_000_000,
+                1_508_000_000,
+                8_000_000,
+            ),
+            tx(
+                "TxSentCancelled",
+                "2019-11-06T03:00:00Z",
+                0,
+                1_000_000_000,
+                0,
+            ),
+            tx("TxReceived", "2019-11-01T01:00:00Z", 7_000_000_000, 0, 0),
+        ];
+        let totals = summarize(
+            &txs,
+            time("2019-11-06T00:00:00Z"),
+            time("2019-11-07T00:00:00Z"),
+        );
+        assert_eq!(
+            totals,
+            DigestTotals {
+                received_count: 1,
+                received: 2_000_000_000,
+                sent_count: 1,
+                sent: 1_000_000_000,
+                fees: 8_000_000,
+                net: 992_000_000,
+            }
+        );
+    }
+}
@@ -0,0 +1,19 @@
+//! Periodic checks that message the user without a command
+//!
+
+/// Balance threshold alerts.
+pub mod alerts;
+/// Scheduled balance and activity digests.
+pub mod digest;
+/// Supervised node events and sync state.
+pub mod node;
+/// Incoming payment notifications.
+pub mod payments;
+/// Recurring and scheduled payments.
+pub mod schedule;
+/// Sent transactions that stay unconfirmed.
+pub mod stuck;
+/// Types kept in the data directory between checks.
+pub mod types;
+/// Runs all enabled checks.
+pub mod watch;
@@ -0,0 +1,120 @@
+use log::warn;
+
+use std::error::Error;
+
+use crate::monitor::types::{NodeSync, Notice};
+use crate::service::node;
+use crate::service::types::NodeStatus;
+use crate::storage;
+use crate::supervisor::types::NodeOptions;
+use crate::types::Context;
+
+/// Data directory file with the node's sync state at the last check.
+const NODE_FILE: &str = "node_sync.json";
+
+/// Returns the supervised node's starts and crashes since the last check,
+/// and a notice when it finishes syncing or falls behind.
+/// The starts and crashes are returned even when the node can't be reached.
+pub fn check(context: &Context) -> Vec<Notice> {
+    let mut notices: Vec<Notice> = context
+        .events
+        .drain()
+        .into_iter()
+        .map(Notice::new)
+        .collect();
+
+    if let Some(options) = &context.node {
+        match sync_notice(context, options) {
+            Ok(Some(notice)) => notices.push(notice),
+            Ok(None) => {}
+            Err(e) => warn!("Node status check failed: {}", e),
+        }
+    }
+    notices
+}
+
+/// Checks the node's sync state against the last check.
+fn sync_notice(context: &Context, options: &NodeOptions) -> Result<Option<Notice>, Box<dyn Error>> {
+    let mut sync: NodeSync = storage::load(&context.data_dir, NODE_FILE)?;
+    let status = node::status(&options.api, &options.dir, &context.http_client)?;
+    let notice = sync_change(sync.synced, &status).map(Notice::new);
+    sync.synced = Some(status.synced());
+    storage::save(&context.data_dir, NODE_FILE, &sync)?;
+    Ok(notice)
+}
+
+/// Message for a change in sync state since the last check, if any.
+pub fn sync_change(synced: Option<bool>, status: &NodeStatus) -> Option<String> {
+    match (synced, status.synced()) {
+        (Some(true), true) | (Some(false), false) => None,
+        (_, true) => Some(format!("Grin node is synced at height {}.", status.height)),
+        (Some(true), false) => Some(format!(
+            "Grin node fell behind and is syncing ({}) at height {}{}.",
+            status.sync_status,
+            status.height,
+            status
+                .highest_height
+                .map(|h| format!(" of {}", h))
+                .unwrap_or_default()
+        )),
+        // Syncing from the start isn't news.
+        (None, false) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(sync_status: &str) -> NodeStatus {
+        NodeStatus {
+            height: 700_000,
+            sync_status: sync_status.to_string(),
+            highest_height: Some(700_100),
+        }
+    }
+
+    #[test]
+    fn sync_changes() {
+        assert_eq!(sync_change(None, &status("header_sync")), None);
+        assert_eq!(
+            sync_change(Some(false), &status("no_sync")),
+            Some("Grin node is synced at height 700000.".to_string())
+        );
+        assert_eq!(sync_change(Some(true), &status("no_sync")), None);
+        assert_eq!(
+            sync_change(Some(true), &status("body_sync")),
+            Some(
+                "Grin node fell behind and is syncing (body_sync) at height 700000 of 700100."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn events_are_pushed_once() {
+        let context = Context::default();
+        context.events.push("Grin node started.".to_string());
+        let notices = check(&context);
+        assert_eq!(notices, vec![Notice::new("Grin node started.".to_string())]);
+        assert!(check(&context).is_empty());
+    }
+
+    #[test]
+    fn events_survive_unreachable_node() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = Context {
+            data_dir: dir.path().to_str().unwrap().to_string(),
+            node: Some(NodeOptions {
+                api: "http://127.0.0.1:1".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        context.events.push("Grin node crashed.".to_string());
+        assert_eq!(
+            check(&context),
+            vec![Notice::new("Grin node crashed.".to_string())]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use askama::Template;
+use grin_wallet_libwallet::{TxLogEntry, TxLogEntryType};
+
+use std::error::Error;
+
+use crate::monitor::types::{AnnouncedPayments, Notice, ReceivedPayment};
+use crate::service::grin;
+use crate::service::types::NanoGrinAmount;
+use crate::storage;
+use crate::template::templates::PaymentReceivedTemplate;
+use crate::types::Context;
+
+/// Data directory file with the payments already announced.
+const ANNOUNCED_FILE: &str = "announced_payments.json";
+
+/// Returns messages for payments received, or confirmed, since the last check.
+pub fn check(context: &Context) -> Result<Vec<Notice>, Box<dyn Error>> {
+    let mut announced: AnnouncedPayments = storage::load(&context.data_dir, ANNOUNCED_FILE)?;
+    let txs = grin::retrieve_txs(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+    let received = received_payments(&txs);
+
+    let messages = if announced.initialized {
+        new_payments(&mut announced, &received)
+            .iter()
+            .map(|(payment, is_new)| Notice::new(render(payment, *is_new)))
+            .collect()
+    } else {
+        // Don't announce the whole history on the first run.
+        new_payments(&mut announced, &received);
+        announced.initialized = true;
+        vec![]
+    };
+
+    storage::save(&context.data_dir, ANNOUNCED_FILE, &announced)?;
+    Ok(messages)
+}
+
+/// Received payments from the transaction log.
+pub fn received_payments(txs: &[TxLogEntry]) -> Vec<ReceivedPayment> {
+    txs.iter()
+        .filter(|tx| tx.tx_type == TxLogEntryType::TxReceived)
+        .map(|tx| {
+            let memos: Vec<String> = tx
+                .messages
+                .as_ref()
+                .map(|m| {
+                    m.messages
+                        .iter()
+                        .filter_map(|data| data.message.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+            ReceivedPayment {
+                id: tx.id,
+                amount: NanoGrinAmount::new(tx.amount_credited as f64).as_grin(),
+                memo: if memos.is_empty() {
+                    None
+                } else {
+                    Some(memos.join(" / "))
+                },
+                confirmed: tx.confirmed,
+            }
+        })
+        .collect()
+}
+
+/// Records payments that are new or newly confirmed.
+/// Returns each one with true if it hasn't been announced before.
+pub fn new_payments(
+    announced: &mut AnnouncedPayments,
+    received: &[ReceivedPayment],
+) -> Vec<(ReceivedPayment, bool)> {
+    let mut changed = vec![];
+    for payment in received {
+        match announced.payments.get(&payment.id) {
+            None => changed.push((payment.clone(), true)),
+            Some(false) if payment.confirmed => changed.push((payment.clone(), false)),
+            _ => continue,
+        }
+        announced.payments.insert(payment.id, payment.confirmed);
+    }
+    changed
+}
+
+fn render(payment: &ReceivedPayment, is_new: bool) -> String {
+    let title = if is_new {
+        "Payment received"
+    } else {
+        "Payment confirmed"
+    };
+    let status = if payment.confirmed {
+        "confirmed"
+    } else {
+        "awaiting confirmation"
+    };
+    let memo = payment.memo.clone().unwrap_or_else(|| "none".to_string());
+    PaymentReceivedTemplate {
+        title,
+        amount: payment.amount,
+        memo: &memo,
+        status,
+        id: payment.id,
+    }
+    .render()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment(id: u32, confirmed: bool) -> ReceivedPayment {
+        ReceivedPayment {
+            id,
+            amount: 1.5,
+            memo: Some("thanks".to_string()),
+            confirmed,
+        }
+    }
+
+    #[test]
+    fn announces_new_payment_once() {
+        let mut announced = AnnouncedPayments::default();
+        let received = vec![payment(1, false)];
+        assert_eq!(
+            new_payments(&mut announced, &received),
+            vec![(payment(1, false), true)]
+        );
+        assert_eq!(new_payments(&mut announced, &received), vec![]);
+    }
+
+    #[test]
+    fn announces_confirmation() {
+        let mut announced = AnnouncedPayments::default();
+        new_payments(&mut announced, &[payment(2, false)]);
+        assert_eq!(
+            new_payments(&mut announced, &[payment(2, true)]),
+            vec![(payment(2, true), false)]
+        );
+        assert_eq!(new_payments(&mut announced, &[payment(2, true)]), vec![]);
+    }
+
+    #[test]
+    fn confirmed_payment_announced_once() {
+        let mut announced = AnnouncedPayments::default();
+        assert_eq!(
+            new_payments(&mut announced, &[payment(3, true)]),
+            vec![(payment(3, true), true)]
+        );
+        assert_eq!(new_payments(&mut announced, &[payment(3, true)]), vec![]);
+    }
+}
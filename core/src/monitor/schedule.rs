@@ -0,0 +1,402 @@
+use askama::Template;
+use chrono::{DateTime, Datelike, Local, TimeZone, Utc};
+use log::warn;
+
+use std::error::Error;
+
+use crate::contacts::types::ResolvedRecipient;
+use crate::monitor::types::{Frequency, Notice, ScheduledPayment, Schedules};
+use crate::security::limits;
+use crate::service::types::GrinAmount;
+use crate::storage;
+use crate::template::templates::{MissedPaymentTemplate, SchedulesTemplate};
+use crate::types::Context;
+
+/// Data directory file with the scheduled payments.
+const SCHEDULES_FILE: &str = "schedules.json";
+
+/// Runs payments that are due. A run that fell due before the bot started was
+/// missed while the bot was down, and waits for approval instead, as do several
+/// runs at once. A check that comes late while the bot is running still sends.
+pub fn check(context: &Context) -> Result<Vec<Notice>, Box<dyn Error>> {
+    check_at(context, Utc::now())
+}
+
+fn check_at(context: &Context, now: DateTime<Utc>) -> Result<Vec<Notice>, Box<dyn Error>> {
+    let mut schedules = load(&context.data_dir)?;
+    let mut notices = vec![];
+
+    for index in 0..schedules.payments.len() {
+        let payment = &mut schedules.payments[index];
+        let id = payment.id;
+        if payment.next_run > now {
+            continue;
+        }
+
+        let on_time = payment.next_run >= context.started;
+        let runs = count_runs(payment.frequency, payment.next_run, now);
+        payment.next_run =
+            next_slot(payment.frequency, &now.with_timezone(&Local)).with_timezone(&Utc);
+
+        if on_time && runs == 1 {
+            let payment = payment.clone();
+            // Save the next run before sending so a crash can't send twice.
+            storage::save(&context.data_dir, SCHEDULES_FILE, &schedules)?;
+            notices.push(Notice::new(run(context, &payment)));
+        } else {
+            let first_missed = payment.missed_runs == 0;
+            payment.missed_runs += runs;
+            warn!(
+                "Scheduled payment {} missed {} run(s)",
+                id, payment.missed_runs
+            );
+            if first_missed {
+                notices.push(missed_notice(payment));
+            }
+        }
+    }
+
+    storage::save(&context.data_dir, SCHEDULES_FILE, &schedules)?;
+    Ok(notices)
+}
+
+/// Sends a scheduled payment and describes the result.
+fn run(context: &Context, payment: &ScheduledPayment) -> String {
+    let recipient = ResolvedRecipient {
+        name: payment.name.clone(),
+        url: payment.destination.clone(),
+        proof_address: payment.proof_address.clone(),
+    };
+    match limits::send(context, GrinAmount::new(payment.amount), &recipient) {
+        Ok(msg) => format!("Scheduled payment {} sent:\n{}", payment.id, msg),
+        Err(e) => {
+            warn!("Scheduled payment {} failed: {}", payment.id, e);
+            format!("Scheduled payment {} failed: {}", payment.id, e)
+        }
+    }
+}
+
+fn missed_notice(payment: &ScheduledPayment) -> Notice {
+    let message = MissedPaymentTemplate {
+        id: payment.id,
+        amount: payment.amount,
+        destination: &payment.destination,
+        missed_runs: payment.missed_runs,
+    }
+    .render()
+    .unwrap();
+    Notice {
+        message,
+        buttons: vec![vec![
+            format!("/schedule approve {}", payment.id),
+            format!("/schedule skip {}", payment.id),
+        ]],
+    }
+}
+
+/// The first run of `frequency` after `after`.
+pub fn next_slot<Tz: TimeZone>(frequency: Frequency, after: &DateTime<Tz>) -> DateTime<Tz> {
+    let mut day = after.date();
+    loop {
+        let matches = match frequency {
+            Frequency::Daily => true,
+            Frequency::Weekly(weekday) => day.weekday().num_days_from_monday() == weekday,
+            Frequency::Monthly(month_day) => day.day() == month_day,
+        };
+        if matches {
+            // Fall back to 1am where midnight doesn't exist because of daylight saving.
+            let slot = day
+                .and_hms_opt(0, 0, 0)
+                .or_else(|| day.and_hms_opt(1, 0, 0));
+            if let Some(slot) = slot {
+                if slot > *after {
+                    return slot;
+                }
+            }
+        }
+        day = day.succ();
+    }
+}
+
+/// Number of runs from `from` up to and including `to`.
+pub fn count_runs(frequency: Frequency, from: DateTime<Utc>, to: DateTime<Utc>) -> u32 {
+    let mut runs = 0;
+    let mut slot = from;
+    while slot <= to {
+        runs += 1;
+        slot = next_slot(frequency, &slot);
+    }
+    runs
+}
+
+/// Adds a scheduled payment and returns the updated list.
+pub fn add(
+    data_dir: &str,
+    amount: GrinAmount,
+    recipient: &ResolvedRecipient,
+    frequency: Frequency,
+) -> Result<String, Box<dyn Error>> {
+    let mut schedules = load(data_dir)?;
+    schedules.last_id += 1;
+    schedules.payments.push(ScheduledPayment {
+        id: schedules.last_id,
+        amount: amount.as_grin(),
+        destination: recipient.url.clone(),
+        name: recipient.name.clone(),
+        proof_address: recipient.proof_address.clone(),
+        frequency,
+        next_run: next_slot(frequency, &Local::now()).with_timezone(&Utc),
+        missed_runs: 0,
+    });
+    storage::save(data_dir, SCHEDULES_FILE, &schedules)?;
+    Ok(render_list(&schedules))
+}
+
+/// Removes a scheduled payment by its id and returns the updated list.
+pub fn remove(data_dir: &str, id: usize) -> Result<String, Box<dyn Error>> {
+    let mut schedules = load(data_dir)?;
+    match schedules.payments.iter().position(|p| p.id == id) {
+        Some(index) => {
+            schedules.payments.remove(index);
+            storage::save(data_dir, SCHEDULES_FILE, &schedules)?;
+            Ok(render_list(&schedules))
+        }
+        None => Ok(format!(
+            "There is no scheduled payment {}.\n\n{}",
+            id,
+            render_list(&schedules)
+        )),
+    }
+}
+
+/// Sends one payment for a schedule with missed runs.
+pub fn approve(context: &Context, id: usize) -> Result<String, Box<dyn Error>> {
+    let mut schedules = load(&context.data_dir)?;
+    let payment = match missed(&mut schedules, id) {
+        Ok(payment) => {
+            payment.missed_runs = 0;
+            payment.clone()
+        }
+        Err(message) => return Ok(message),
+    };
+    storage::save(&context.data_dir, SCHEDULES_FILE, &schedules)?;
+    Ok(run(context, &payment))
+}
+
+/// Drops the missed runs of a schedule without sending.
+pub fn skip(data_dir: &str, id: usize) -> Result<String, Box<dyn Error>> {
+    let mut schedules = load(data_dir)?;
+    match missed(&mut schedules, id) {
+        Ok(payment) => payment.missed_runs = 0,
+        Err(message) => return Ok(message),
+    }
+    storage::save(data_dir, SCHEDULES_FILE, &schedules)?;
+    Ok(format!(
+        "Skipped the missed runs of scheduled payment {}.",
+        id
+    ))
+}
+
+/// The schedule with an id, if it has missed runs, or why it can't be approved or skipped.
+fn missed(schedules: &mut Schedules, id: usize) -> Result<&mut ScheduledPayment, String> {
+    match schedules.payments.iter_mut().find(|p| p.id == id) {
+        Some(payment) if payment.missed_runs > 0 => Ok(payment),
+        Some(_) => Err(format!("Scheduled payment {} has no missed runs.", id)),
+        None => Err(format!("There is no scheduled payment {}.", id)),
+    }
+}
+
+/// Loads the schedules. Schedules saved before they had ids are given one.
+fn load(data_dir: &str) -> Result<Schedules, Box<dyn Error>> {
+    let mut schedules: Schedules = storage::load(data_dir, SCHEDULES_FILE)?;
+    let highest = schedules.payments.iter().map(|p| p.id).max().unwrap_or(0);
+    schedules.last_id = schedules.last_id.max(highest);
+    for payment in schedules.payments.iter_mut().filter(|p| p.id == 0) {
+        schedules.last_id += 1;
+        payment.id = schedules.last_id;
+    }
+    Ok(schedules)
+}
+
+/// Lists the scheduled payments.
+pub fn list(data_dir: &str) -> Result<String, Box<dyn Error>> {
+    let schedules = load(data_dir)?;
+    Ok(render_list(&schedules))
+}
+
+fn render_list(schedules: &Schedules) -> String {
+    let next_runs: Vec<String> = schedules
+        .payments
+        .iter()
+        .map(|p| {
+            p.next_run
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .collect();
+    SchedulesTemplate {
+        payments: &schedules.payments,
+        next_runs: &next_runs,
+    }
+    .render()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(t: &str) -> DateTime<Utc> {
+        t.parse().unwrap()
+    }
+
+    #[test]
+    fn next_monthly_slot() {
+        let frequency = Frequency::Monthly(1);
+        assert_eq!(
+            next_slot(frequency, &time("2019-11-06T10:00:00Z")),
+            time("2019-12-01T00:00:00Z")
+        );
+        assert_eq!(
+            next_slot(frequency, &time("2019-12-01T00:00:00Z")),
+            time("2020-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn next_weekly_slot() {
+        // 2019-11-06 is a Wednesday.
+        let frequency = Frequency::parse(&["weekly", "fri"]).unwrap();
+        assert_eq!(
+            next_slot(frequency, &time("2019-11-06T10:00:00Z")),
+            time("2019-11-08T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn runs_missed_while_down() {
+        let frequency = Frequency::Daily;
+        assert_eq!(
+            count_runs(
+                frequency,
+                time("2019-11-01T00:00:00Z"),
+                time("2019-11-01T00:01:00Z")
+            ),
+            1
+        );
+        assert_eq!(
+            count_runs(
+                frequency,
+                time("2019-11-01T00:00:00Z"),
+                time("2019-11-04T12:00:00Z")
+            ),
+            4
+        );
+    }
+
+    #[test]
+    fn late_checks_still_send() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = time("2019-11-06T10:00:00Z");
+        let context = Context {
+            data_dir: dir.path().to_str().unwrap().to_string(),
+            started: time("2019-11-05T12:00:00Z"),
+            ..Default::default()
+        };
+        let payment = |id, next_run| ScheduledPayment {
+            id,
+            amount: 1.0,
+            destination: "http://127.0.0.1:1".to_string(),
+            name: None,
+            proof_address: None,
+            frequency: Frequency::Daily,
+            next_run: time(next_run),
+            missed_runs: 0,
+        };
+        let schedules = Schedules {
+            last_id: 2,
+            payments: vec![
+                // Due hours ago while the bot was running: sent, whatever the watch interval.
+                payment(1, "2019-11-06T00:00:00Z"),
+                // Due before the bot started: waits for approval.
+                payment(2, "2019-11-05T00:00:00Z"),
+            ],
+        };
+        storage::save(&context.data_dir, SCHEDULES_FILE, &schedules).unwrap();
+
+        let notices = check_at(&context, now).unwrap();
+        assert_eq!(notices.len(), 2);
+        assert!(notices[0].message.starts_with("Scheduled payment 1 failed"));
+        assert!(notices[1].message.contains("Scheduled payment missed"));
+        let schedules = load(&context.data_dir).unwrap();
+        assert_eq!(schedules.payments[0].missed_runs, 0);
+        assert_eq!(schedules.payments[1].missed_runs, 2);
+    }
+
+    #[test]
+    fn parse_frequency() {
+        assert_eq!(
+            Frequency::parse(&["monthly", "1"]),
+            Some(Frequency::Monthly(1))
+        );
+        assert_eq!(Frequency::parse(&["monthly", "31"]), None);
+        assert_eq!(
+            Frequency::parse(&["weekly", "Monday"]),
+            Some(Frequency::Weekly(0))
+        );
+        assert_eq!(Frequency::parse(&["yearly"]), None);
+    }
+
+    #[test]
+    fn ids_survive_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_str().unwrap();
+        let recipient = |url: &str| ResolvedRecipient {
+            name: None,
+            url: url.to_string(),
+            proof_address: None,
+        };
+        for url in &["https://one.org", "https://two.org", "https://three.org"] {
+            add(
+                data_dir,
+                GrinAmount::new(1.0),
+                &recipient(url),
+                Frequency::Daily,
+            )
+            .unwrap();
+        }
+
+        remove(data_dir, 1).unwrap();
+        let mut schedules = load(data_dir).unwrap();
+        let ids: Vec<usize> = schedules.payments.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert!(remove(data_dir, 1)
+            .unwrap()
+            .starts_with("There is no scheduled payment 1."));
+
+        // A stale button for a removed schedule can't reach another one.
+        schedules.payments[1].missed_runs = 1;
+        storage::save(data_dir, SCHEDULES_FILE, &schedules).unwrap();
+        assert_eq!(
+            skip(data_dir, 1).unwrap(),
+            "There is no scheduled payment 1."
+        );
+        assert_eq!(
+            skip(data_dir, 2).unwrap(),
+            "Scheduled payment 2 has no missed runs."
+        );
+        assert!(skip(data_dir, 3).unwrap().starts_with("Skipped"));
+
+        // Ids aren't reused after the newest schedule is removed.
+        remove(data_dir, 3).unwrap();
+        add(
+            data_dir,
+            GrinAmount::new(1.0),
+            &recipient("https://four.org"),
+            Frequency::Daily,
+        )
+        .unwrap();
+        assert_eq!(load(data_dir).unwrap().payments[1].id, 4);
+    }
+}
@@ -0,0 +1,167 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use grin_wallet_libwallet::{TxLogEntry, TxLogEntryType};
+
+use std::error::Error;
+
+use crate::monitor::types::{FlaggedTxs, Notice, StuckTx};
+use crate::service::grin;
+use crate::service::types::NanoGrinAmount;
+use crate::storage;
+use crate::template::templates::StuckTxTemplate;
+use crate::types::Context;
+
+/// Data directory file with the transactions already flagged.
+const FLAGGED_FILE: &str = "flagged_txs.json";
+
+/// Returns a notice for each sent transaction that became stuck since the last check.
+pub fn check(context: &Context) -> Result<Vec<Notice>, Box<dyn Error>> {
+    let mut flagged: FlaggedTxs = storage::load(&context.data_dir, FLAGGED_FILE)?;
+    let info = grin::wallet_info(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+    let txs = grin::retrieve_txs(
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    )?;
+
+    let stuck = stuck_txs(
+        &txs,
+        info.last_confirmed_height,
+        Utc::now(),
+        context.stuck_tx_blocks,
+    );
+    let notices = stuck
+        .iter()
+        .filter(|tx| !flagged.tx_ids.contains(&tx.id))
+        .map(render)
+        .collect();
+
+    // Forget transactions that are no longer stuck so they can be flagged again.
+    flagged.tx_ids = stuck.iter().map(|tx| tx.id).collect();
+    storage::save(&context.data_dir, FLAGGED_FILE, &flagged)?;
+    Ok(notices)
+}
+
+/// Sent transactions unconfirmed for more than `max_blocks`.
+pub fn stuck_txs(
+    txs: &[TxLogEntry],
+    height: u64,
+    now: DateTime<Utc>,
+    max_blocks: u64,
+) -> Vec<StuckTx> {
+    txs.iter()
+        .filter(|tx| tx.tx_type == TxLogEntryType::TxSent && !tx.confirmed)
+        .map(|tx| StuckTx {
+            id: tx.id,
+            age: age_in_blocks(tx, height, now),
+            locked: NanoGrinAmount::new(tx.amount_debited as f64).as_grin(),
+        })
+        .filter(|tx| tx.age > max_blocks)
+        .collect()
+}
+
+/// Blocks since the transaction was created. Uses the creation
+/// time at one block per minute if the height wasn't recorded.
+fn age_in_blocks(tx: &TxLogEntry, height: u64, now: DateTime<Utc>) -> u64 {
+    match tx.kernel_lookup_min_height {
+        Some(created_height) => height.saturating_sub(created_height),
+        None => {
+            let minutes = now.signed_duration_since(tx.creation_ts).num_minutes();
+            if minutes > 0 {
+                minutes as u64
+            } else {
+                0
+            }
+        }
+    }
+}
+
+fn render(tx: &StuckTx) -> Notice {
+    let message = StuckTxTemplate {
+        id: tx.id,
+        age: tx.age,
+        locked: tx.locked,
+    }
+    .render()
+    .unwrap();
+    Notice {
+        message,
+        buttons: vec![vec![
+            format!("/repost {}", tx.id),
+            format!("/cancel {}", tx.id),
+        ]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(id: u32, tx_type: &str, confirmed: bool, height: Option<u64>) -> TxLogEntry {
+        let json = format!(
+            r#"{{
+              "parent_key_id": "0200000000000000000000000000000000",
+              "id": {},
+              "tx_slate_id": null,
+              "tx_type": "{}",
+              "creation_ts": "2019-11-01T12:00:00Z",
+              "confirmation_ts": null,
+              "confirmed": {},
+              "num_inputs": 1,
+              "num_outputs": 1,
+              "amount_credited": 500000000,
+              "amount_debited": 2000000000,
+              "fee": 8000000,
+              "ttl_cutoff_height": null,
+              "messages": null,
+              "stored_tx": null,
+              "kernel_excess": null,
+              "kernel_lookup_min_height": {},
+              "payment_proof": null
+            }}"#,
+            id,
+            tx_type,
+            confirmed,
+            height.map(|h| h.to_string()).unwrap_or("null".into())
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2019-11-01T13:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn flags_old_unconfirmed_send() {
+        let txs = vec![tx(1, "TxSent", false, Some(100))];
+        assert_eq!(
+            stuck_txs(&txs, 200, now(), 60),
+            vec![StuckTx {
+                id: 1,
+                age: 100,
+                locked: 2.0
+            }]
+        );
+    }
+
+    #[test]
+    fn ignores_recent_confirmed_and_received() {
+        let txs = vec![
+            tx(1, "TxSent", false, Some(180)),
+            tx(2, "TxSent", true, Some(100)),
+            tx(3, "TxReceived", false, Some(100)),
+        ];
+        assert_eq!(stuck_txs(&txs, 200, now(), 60), vec![]);
+    }
+
+    #[test]
+    fn uses_creation_time_without_height() {
+        let txs = vec![tx(4, "TxSent", false, None)];
+        assert_eq!(stuck_txs(&txs, 200, now(), 30)[0].age, 60);
+        assert_eq!(stuck_txs(&txs, 200, now(), 60), vec![]);
+    }
+}
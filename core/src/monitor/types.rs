@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A message from a periodic check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    pub message: String,
+    /// Rows of commands to offer as buttons.
+    pub buttons: Vec<Vec<String>>,
+}
+
+impl Notice {
+    pub fn new(message: String) -> Self {
+        Notice {
+            message,
+            buttons: vec![],
+        }
+    }
+}
+
+/// Sync state of the supervised node at the last check.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct NodeSync {
+    /// None before the first successful check.
+    pub synced: Option<bool>,
+}
+
+/// Received payments that have already been announced.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct AnnouncedPayments {
+    /// False until the first check. Existing payments are recorded
+    /// on the first check without being announced.
+    pub initialized: bool,
+    /// Transaction id and whether it was confirmed when last announced.
+    pub payments: BTreeMap<u32, bool>,
+}
+
+/// A received payment from the wallet's transaction log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedPayment {
+    pub id: u32,
+    pub amount: f64,
+    pub memo: Option<String>,
+    pub confirmed: bool,
+}
+
+/// Sent transactions that have already been flagged as stuck.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct FlaggedTxs {
+    pub tx_ids: BTreeSet<u32>,
+}
+
+/// A sent transaction that has gone unconfirmed too long.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StuckTx {
+    pub id: u32,
+    /// Blocks since the transaction was created.
+    pub age: u64,
+    pub locked: f64,
+}
+
+/// Which side of the threshold a balance alert watches.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AlertDirection {
+    Below,
+    Above,
+}
+
+impl fmt::Display for AlertDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlertDirection::Below => write!(f, "below"),
+            AlertDirection::Above => write!(f, "above"),
+        }
+    }
+}
+
+/// A rule on the spendable balance.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub direction: AlertDirection,
+    pub threshold: f64,
+    /// True after the alert fired, until the balance moves back past the threshold.
+    pub triggered: bool,
+}
+
+/// Balance alerts set by the user.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Alerts {
+    pub alerts: Vec<Alert>,
+}
+
+/// How often a digest is sent.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+/// When to send the digest. Weekly digests go out on Mondays.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DigestSchedule {
+    pub period: DigestPeriod,
+    /// Local time of day.
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl DigestSchedule {
+    /// Parses a schedule like "daily 09:00" or "weekly 18:30".
+    pub fn parse(period: &str, time: &str) -> Option<Self> {
+        let period = match period {
+            "daily" => DigestPeriod::Daily,
+            "weekly" => DigestPeriod::Weekly,
+            _ => return None,
+        };
+        let mut parts = time.split(':');
+        let hour = parts.next()?.parse::<u32>().ok()?;
+        let minute = parts.next()?.parse::<u32>().ok()?;
+        if parts.next().is_some() || hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(DigestSchedule {
+            period,
+            hour,
+            minute,
+        })
+    }
+}
+
+impl fmt::Display for DigestSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let period = match self.period {
+            DigestPeriod::Daily => "daily",
+            DigestPeriod::Weekly => "weekly on Mondays",
+        };
+        write!(f, "{} at {:02}:{:02}", period, self.hour, self.minute)
+    }
+}
+
+/// Digest schedule set from chat and when the last digest was sent.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct DigestState {
+    /// Schedule set with /digest. Replaces the schedule in config.yml.
+    pub schedule: Option<DigestSchedule>,
+    /// True once /digest has set a schedule, or turned digests off.
+    pub overridden: bool,
+    pub last_sent: Option<DateTime<Utc>>,
+}
+
+/// Transaction totals for a digest, in nanogrin.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DigestTotals {
+    pub received_count: u32,
+    pub received: u64,
+    pub sent_count: u32,
+    pub sent: u64,
+    pub fees: u64,
+    /// Change in the wallet total.
+    pub net: i64,
+}
+
+/// How often a scheduled payment runs. Runs start at midnight local time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    /// Day of the week, 0 for Monday.
+    Weekly(u32),
+    /// Day of the month, 1 to 28.
+    Monthly(u32),
+}
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+impl Frequency {
+    /// Parses "daily", "weekly mon" or "monthly 1".
+    pub fn parse(tokens: &[&str]) -> Option<Self> {
+        match tokens {
+            ["daily"] => Some(Frequency::Daily),
+            ["weekly", day] => WEEKDAYS
+                .iter()
+                .position(|d| day.to_lowercase().starts_with(d))
+                .map(|d| Frequency::Weekly(d as u32)),
+            ["monthly", day] => match day.parse::<u32>() {
+                Ok(day) if (1..=28).contains(&day) => Some(Frequency::Monthly(day)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Frequency::Daily => write!(f, "daily"),
+            Frequency::Weekly(day) => write!(f, "weekly on {}", WEEKDAYS[*day as usize % 7]),
+            Frequency::Monthly(day) => write!(f, "monthly on day {}", day),
+        }
+    }
+}
+
+/// A recurring payment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduledPayment {
+    /// Number the schedule is addressed by in commands. Never reused.
+    #[serde(default)]
+    pub id: usize,
+    pub amount: f64,
+    pub destination: String,
+    /// Contact name the destination was resolved from.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub proof_address: Option<String>,
+    pub frequency: Frequency,
+    pub next_run: DateTime<Utc>,
+    /// Runs missed while the bot was down, waiting for /schedule approve or skip.
+    pub missed_runs: u32,
+}
+
+/// Recurring payments set by the user.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Schedules {
+    pub payments: Vec<ScheduledPayment>,
+    /// The last id given to a schedule.
+    #[serde(default)]
+    pub last_id: usize,
+}
@@ -0,0 +1,52 @@
+use log::warn;
+
+use crate::monitor::types::Notice;
+use crate::monitor::{alerts, digest, node, payments, schedule, stuck};
+use crate::types::Context;
+
+/// Runs every enabled check and joins their notices into one.
+/// Returns None when there is nothing to tell the user.
+pub fn run(context: &Context) -> Option<Notice> {
+    let mut notices = node::check(context);
+
+    if context.notify_payments {
+        match payments::check(context) {
+            Ok(mut n) => notices.append(&mut n),
+            Err(e) => warn!("Payment check failed: {}", e),
+        }
+    }
+
+    if context.stuck_tx_blocks > 0 {
+        match stuck::check(context) {
+            Ok(mut n) => notices.append(&mut n),
+            Err(e) => warn!("Stuck transaction check failed: {}", e),
+        }
+    }
+
+    match schedule::check(context) {
+        Ok(mut n) => notices.append(&mut n),
+        Err(e) => warn!("Scheduled payment check failed: {}", e),
+    }
+
+    match alerts::check(context) {
+        Ok(mut n) => notices.append(&mut n),
+        Err(e) => warn!("Balance alert check failed: {}", e),
+    }
+
+    match digest::check(context) {
+        Ok(mut n) => notices.append(&mut n),
+        Err(e) => warn!("Digest failed: {}", e),
+    }
+
+    if notices.is_empty() {
+        None
+    } else {
+        let message = notices
+            .iter()
+            .map(|n| n.message.clone())
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        let buttons = notices.into_iter().flat_map(|n| n.buttons).collect();
+        Some(Notice { message, buttons })
+    }
+}
@@ -0,0 +1,134 @@
+use log::error;
+use rand::seq::index;
+
+use std::error::Error;
+
+use crate::security::types::{BackupNotVerifiedError, BackupQuiz, BackupState, UnverifiedBackup};
+use crate::storage;
+use crate::types::Context;
+
+/// Data directory file with the backup state.
+const BACKUP_FILE: &str = "backup.json";
+
+/// Number of words asked for.
+const QUIZ_WORDS: usize = 3;
+
+/// Starts the backup check for a new wallet's recovery phrase.
+pub fn quiz(data_dir: &str, seed: &str) -> Result<BackupQuiz, Box<dyn Error>> {
+    let words: Vec<String> = seed.split_whitespace().map(|w| w.to_string()).collect();
+    if words.len() < QUIZ_WORDS {
+        return Err("recovery phrase is too short".into());
+    }
+    let mut positions = index::sample(&mut rand::thread_rng(), words.len(), QUIZ_WORDS).into_vec();
+    positions.sort_unstable();
+    storage::save(data_dir, BACKUP_FILE, &BackupState { pending: true })?;
+    Ok(BackupQuiz { words, positions })
+}
+
+/// Asks for the quiz words.
+pub fn prompt(quiz: &BackupQuiz) -> String {
+    let p: Vec<usize> = quiz.positions.iter().map(|p| p + 1).collect();
+    format!(
+        "To check you wrote it down, send /backup followed by words {}, {} and {} of your recovery phrase, e.g. <pre>/backup word{} word{} word{}</pre>",
+        p[0], p[1], p[2], p[0], p[1], p[2]
+    )
+}
+
+/// Checks the quiz words, and marks the backup verified if they match.
+pub fn verify(data_dir: &str, quiz: &BackupQuiz, words: &[String]) -> Result<bool, Box<dyn Error>> {
+    let matches = words.len() == quiz.positions.len()
+        && quiz
+            .positions
+            .iter()
+            .zip(words)
+            .all(|(p, word)| quiz.words[*p].eq_ignore_ascii_case(word));
+    if matches {
+        mark_verified(data_dir)?;
+    }
+    Ok(matches)
+}
+
+/// Marks the backup verified. Also run from the host when the phrase is no longer in memory.
+pub fn mark_verified(data_dir: &str) -> Result<(), Box<dyn Error>> {
+    storage::save(data_dir, BACKUP_FILE, &BackupState { pending: false })
+}
+
+/// Whether a wallet created with /create still waits for its backup check.
+/// An unreadable file counts as waiting.
+pub fn pending(data_dir: &str) -> bool {
+    match storage::load::<BackupState>(data_dir, BACKUP_FILE) {
+        Ok(state) => state.pending,
+        Err(e) => {
+            error!(
+                "Can't read {}, treating the backup as unverified: {}",
+                BACKUP_FILE, e
+            );
+            true
+        }
+    }
+}
+
+/// Reminder to show with a send, or an error if sends are blocked until the backup check.
+pub fn check_send(context: &Context) -> Result<Option<String>, BackupNotVerifiedError> {
+    if !pending(&context.data_dir) {
+        return Ok(None);
+    }
+    match context.unverified_backup {
+        UnverifiedBackup::Remind => Ok(Some(
+            "Reminder: your recovery phrase backup isn't verified yet. Send /backup to check it."
+                .to_string(),
+        )),
+        UnverifiedBackup::Block => Err(BackupNotVerifiedError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_quiz() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_str().unwrap();
+        let seed =
+            "abandon ability able about above absent absorb abstract absurd abuse access accident";
+        let quiz = quiz(data_dir, seed).unwrap();
+        assert_eq!(quiz.positions.len(), 3);
+        assert!(quiz.positions.windows(2).all(|p| p[0] < p[1]));
+        assert!(pending(data_dir));
+        assert!(!format!("{:?}", quiz).contains("abandon"));
+
+        let answer: Vec<String> = quiz
+            .positions
+            .iter()
+            .map(|p| quiz.words[*p].clone())
+            .collect();
+        let wrong = vec!["zoo".to_string(); 3];
+        assert!(!verify(data_dir, &quiz, &wrong).unwrap());
+        assert!(pending(data_dir));
+        assert!(verify(data_dir, &quiz, &answer[..2]).is_ok());
+        assert!(pending(data_dir));
+        assert!(verify(data_dir, &quiz, &answer).unwrap());
+        assert!(!pending(data_dir));
+    }
+
+    #[test]
+    fn sends_until_verified() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut context = Context {
+            data_dir: dir.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        storage::save(
+            &context.data_dir,
+            BACKUP_FILE,
+            &BackupState { pending: true },
+        )
+        .unwrap();
+        assert!(check_send(&context).unwrap().is_some());
+        context.unverified_backup = UnverifiedBackup::Block;
+        assert_eq!(check_send(&context), Err(BackupNotVerifiedError));
+        mark_verified(&context.data_dir).unwrap();
+        assert_eq!(check_send(&context), Ok(None));
+    }
+}
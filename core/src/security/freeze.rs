@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use log::error;
+
+use std::error::Error;
+
+use crate::controller::types::Action;
+use crate::security::types::FreezeState;
+use crate::storage;
+
+/// Data directory file with the kill switch.
+const FREEZE_FILE: &str = "freeze.json";
+
+/// When the wallet was frozen, if it is. An unreadable file counts as frozen.
+pub fn frozen_since(data_dir: &str) -> Option<DateTime<Utc>> {
+    match storage::load::<FreezeState>(data_dir, FREEZE_FILE) {
+        Ok(state) => state.since,
+        Err(e) => {
+            error!(
+                "Can't read {}, treating the wallet as frozen: {}",
+                FREEZE_FILE, e
+            );
+            Some(Utc::now())
+        }
+    }
+}
+
+/// Freezes the wallet. Freezing again keeps the original time.
+pub fn freeze(data_dir: &str) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    let mut state: FreezeState = storage::load(data_dir, FREEZE_FILE)?;
+    let since = *state.since.get_or_insert_with(Utc::now);
+    storage::save(data_dir, FREEZE_FILE, &state)?;
+    Ok(since)
+}
+
+/// Clears the kill switch. Only called from the host command line.
+pub fn unfreeze(data_dir: &str) -> Result<bool, Box<dyn Error>> {
+    let was_frozen = storage::load::<FreezeState>(data_dir, FREEZE_FILE)?
+        .since
+        .is_some();
+    storage::save(data_dir, FREEZE_FILE, &FreezeState::default())?;
+    Ok(was_frozen)
+}
+
+/// Whether an action can move funds, and so is refused while frozen.
+pub fn moves_funds(action: &Action) -> bool {
+    match action {
+        Action::Send(..)
+        | Action::Confirm(_)
+        | Action::Repost(..)
+        | Action::AddSchedule(..)
+        | Action::ApproveSchedule(..)
+        | Action::Code(..) => true,
+        Action::WithCode(_, _, inner) | Action::SentBy(_, inner) => moves_funds(inner),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freeze_until_unfrozen() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_str().unwrap();
+        assert_eq!(frozen_since(data_dir), None);
+
+        let since = freeze(data_dir).unwrap();
+        assert_eq!(freeze(data_dir).unwrap(), since);
+        assert_eq!(frozen_since(data_dir), Some(since));
+
+        assert!(unfreeze(data_dir).unwrap());
+        assert_eq!(frozen_since(data_dir), None);
+    }
+
+    #[test]
+    fn value_moving_actions() {
+        assert!(moves_funds(&Action::Confirm(1)));
+        assert!(moves_funds(&Action::WithCode(
+            1,
+            "123456".to_string(),
+            Box::new(Action::ApproveSchedule(1, 1))
+        )));
+        assert!(!moves_funds(&Action::Balance(1)));
+    }
+}
@@ -0,0 +1,270 @@
+use chrono::{DateTime, Duration, Utc};
+use log::{error, warn};
+
+use std::error::Error;
+
+use crate::contacts::types::ResolvedRecipient;
+use crate::security::freeze;
+use crate::security::types::{
+    FrozenError, Limit, LimitExceededError, ReadOnlyError, Spend, SpendHistory, SpendingLimits,
+};
+use crate::service::grin;
+use crate::service::types::GrinAmount;
+use crate::storage;
+use crate::types::Context;
+
+/// Data directory file with recent sends.
+const SPEND_HISTORY_FILE: &str = "spend_history.json";
+
+/// Sends Grin if the amount is within the spending limits, and records the send.
+/// Every send from chat or a schedule goes through here.
+pub fn send(
+    context: &Context,
+    amount: GrinAmount,
+    recipient: &ResolvedRecipient,
+) -> Result<String, Box<dyn Error>> {
+    if context.read_only {
+        warn!(
+            "Refused to send {} to {} in read-only mode",
+            amount.as_grin(),
+            recipient
+        );
+        return Err(Box::new(ReadOnlyError));
+    }
+    if let Some(since) = freeze::frozen_since(&context.data_dir) {
+        warn!(
+            "Refused to send {} to {} while frozen",
+            amount.as_grin(),
+            recipient
+        );
+        return Err(Box::new(FrozenError(since)));
+    }
+    if let Err(e) = allowed(context, amount) {
+        warn!(
+            "Refused to send {} to {}: {}",
+            amount.as_grin(),
+            recipient,
+            e
+        );
+        return Err(e);
+    }
+
+    // Recorded before sending, so a send that can't be counted against the
+    // limits doesn't happen, and a crash mid-send still counts.
+    let spend = Spend {
+        time: Utc::now(),
+        amount: amount.as_grin(),
+        pending: true,
+    };
+    let pending = spend.clone();
+    if let Err(e) = update_history(&context.data_dir, |spends| spends.push(pending)) {
+        warn!(
+            "Refused to send {} to {}: failed to record the send: {}",
+            amount.as_grin(),
+            recipient,
+            e
+        );
+        return Err(e);
+    }
+
+    match grin::send(
+        amount,
+        &recipient.url,
+        recipient.proof_address.as_ref().map(String::as_str),
+        &context.wallet_dir,
+        &context.owner_endpoint,
+        &context.http_client,
+    ) {
+        Ok(message) => {
+            let confirmed = update_history(&context.data_dir, |spends| {
+                for s in spends.iter_mut().filter(|s| **s == spend) {
+                    s.pending = false;
+                }
+            });
+            if let Err(e) = confirmed {
+                error!(
+                    "Sent {} to {} but failed to record it as sent: {}",
+                    amount.as_grin(),
+                    recipient,
+                    e
+                );
+            }
+            Ok(message)
+        }
+        Err(send_error) => {
+            let removed =
+                update_history(&context.data_dir, |spends| spends.retain(|s| *s != spend));
+            if let Err(e) = removed {
+                error!(
+                    "Failed to remove the pending send of {} to {}: {}",
+                    amount.as_grin(),
+                    recipient,
+                    e
+                );
+            }
+            Err(send_error)
+        }
+    }
+}
+
+/// Loads the spend history, drops sends older than a week, applies `change`
+/// and saves it.
+fn update_history<F>(data_dir: &str, change: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut Vec<Spend>),
+{
+    // Only the last week matters for the limits.
+    let mut history: SpendHistory = storage::load(data_dir, SPEND_HISTORY_FILE)?;
+    let now = Utc::now();
+    history
+        .spends
+        .retain(|spend| now.signed_duration_since(spend.time) < Duration::days(7));
+    change(&mut history.spends);
+    storage::save(data_dir, SPEND_HISTORY_FILE, &history)
+}
+
+/// Checks an amount against the limits and the recorded sends.
+pub fn allowed(context: &Context, amount: GrinAmount) -> Result<(), Box<dyn Error>> {
+    let history: SpendHistory = storage::load(&context.data_dir, SPEND_HISTORY_FILE)?;
+    check(&context.limits, &history, amount.as_grin(), Utc::now())?;
+    Ok(())
+}
+
+/// Checks an amount against the limits, given the sends so far.
+pub fn check(
+    limits: &SpendingLimits,
+    history: &SpendHistory,
+    amount: f64,
+    now: DateTime<Utc>,
+) -> Result<(), LimitExceededError> {
+    let rules = [
+        (Limit::PerTx, limits.per_tx, None),
+        (Limit::PerDay, limits.per_day, Some(Duration::days(1))),
+        (Limit::PerWeek, limits.per_week, Some(Duration::days(7))),
+    ];
+    for (limit, max, period) in rules.iter() {
+        let max = match max {
+            Some(max) => *max,
+            None => continue,
+        };
+        let spent = match period {
+            Some(period) => spent_since(history, now - *period),
+            None => 0.0,
+        };
+        if spent + amount > max {
+            return Err(LimitExceededError {
+                limit: *limit,
+                max,
+                amount,
+                spent,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn spent_since(history: &SpendHistory, since: DateTime<Utc>) -> f64 {
+    history
+        .spends
+        .iter()
+        .filter(|spend| spend.time > since)
+        .map(|spend| spend.amount)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(now: DateTime<Utc>) -> SpendHistory {
+        SpendHistory {
+            spends: vec![
+                Spend {
+                    time: now - Duration::hours(30),
+                    amount: 4.0,
+                    pending: false,
+                },
+                Spend {
+                    time: now - Duration::hours(2),
+                    amount: 3.0,
+                    pending: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn within_limits() {
+        let now = Utc::now();
+        let limits = SpendingLimits {
+            per_tx: Some(2.0),
+            per_day: Some(5.0),
+            per_week: Some(10.0),
+        };
+        assert_eq!(check(&limits, &history(now), 2.0, now), Ok(()));
+        assert_eq!(
+            check(&SpendingLimits::default(), &history(now), 100.0, now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn over_limits() {
+        let now = Utc::now();
+        let limits = SpendingLimits {
+            per_tx: Some(2.0),
+            per_day: Some(5.0),
+            per_week: Some(8.0),
+        };
+        assert_eq!(
+            check(&limits, &history(now), 2.5, now).unwrap_err().limit,
+            Limit::PerTx
+        );
+
+        let limits = SpendingLimits {
+            per_tx: None,
+            ..limits
+        };
+        let error = check(&limits, &history(now), 2.5, now).unwrap_err();
+        assert_eq!(error.limit, Limit::PerDay);
+        assert_eq!(error.spent, 3.0);
+
+        let limits = SpendingLimits {
+            per_day: None,
+            ..limits
+        };
+        let error = check(&limits, &history(now), 1.5, now).unwrap_err();
+        assert_eq!(error.limit, Limit::PerWeek);
+        assert_eq!(error.spent, 7.0);
+    }
+
+    fn recipient() -> ResolvedRecipient {
+        ResolvedRecipient {
+            name: None,
+            url: "http://127.0.0.1:1".to_string(),
+            proof_address: None,
+        }
+    }
+
+    #[test]
+    fn refuses_unrecorded_send() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let context = Context {
+            data_dir: file.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        assert!(send(&context, GrinAmount::new(1.0), &recipient()).is_err());
+    }
+
+    #[test]
+    fn failed_send_is_not_counted() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = Context {
+            data_dir: dir.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        assert!(send(&context, GrinAmount::new(1.0), &recipient()).is_err());
+        let history: SpendHistory = storage::load(&context.data_dir, SPEND_HISTORY_FILE).unwrap();
+        assert!(history.spends.is_empty());
+    }
+}
@@ -0,0 +1,16 @@
+/// Recovery phrase backup check
+pub mod backup;
+/// Emergency kill switch
+pub mod freeze;
+/// Spending limits
+pub mod limits;
+/// Per-user roles and read-only mode
+pub mod roles;
+/// Recovery phrase delivery
+pub mod seed;
+/// PIN lock
+pub mod session;
+/// Authenticator codes (TOTP)
+pub mod totp;
+/// Security types
+pub mod types;
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use crate::controller::types::Action;
+use crate::security::types::Role;
+
+/// Authorized users and their roles, from config.yml.
+pub type Users = BTreeMap<String, Role>;
+
+/// Role a user needs for an action.
+pub fn required(action: &Action) -> Role {
+    use Action::*;
+    match action {
+        Create(..) | Backup(..) | AddSchedule(..) | RemoveSchedule(..) | AddContact(..)
+        | RemoveContact(..) => Role::Admin,
+        Send(..) | Confirm(_) | Repost(..) | Cancel(..) | AddAlert(..) | RemoveAlert(..)
+        | SetDigest(..) | ApproveSchedule(..) | SkipSchedule(..) => Role::Spender,
+        WithCode(_, _, inner) | SentBy(_, inner) => required(inner),
+        Home(_) | Abort(_) | Balance(_) | Status(_) | Address(_) | Alerts(_) | Digest(_)
+        | Schedules(_) | Contacts(_) | Help(_) | NoUsername(_) | WrongUsername(_)
+        | NotPermitted(..) | ModeNotSupported(_) | Back(_) | CommandError(..) | Unknown(_)
+        | Tick(_) | Code(..) | Unlock(..) | Lock(_) | Freeze(_) => Role::Viewer,
+    }
+}
+
+/// Whether an action is allowed while read_only is set.
+pub fn allowed_read_only(action: &Action) -> bool {
+    match action {
+        Action::Freeze(_) => true,
+        _ => required(action) == Role::Viewer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_of_actions() {
+        assert_eq!(required(&Action::Balance(1)), Role::Viewer);
+        assert_eq!(required(&Action::Confirm(1)), Role::Spender);
+        // Anyone who can see the wallet can stop it spending.
+        assert_eq!(required(&Action::Freeze(1)), Role::Viewer);
+        assert_eq!(
+            required(&Action::WithCode(
+                1,
+                "123456".to_string(),
+                Box::new(Action::RemoveSchedule(1, 1))
+            )),
+            Role::Admin
+        );
+        assert!(Role::Admin > Role::Spender && Role::Spender > Role::Viewer);
+    }
+
+    #[test]
+    fn read_only_actions() {
+        assert!(allowed_read_only(&Action::Balance(1)));
+        assert!(allowed_read_only(&Action::Freeze(1)));
+        assert!(!allowed_read_only(&Action::Cancel(1, 2)));
+    }
+}
@@ -0,0 +1,96 @@
+use askama::Template;
+use log::error;
+
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::security::types::SeedDelivery;
+use crate::template::templates::{SeedFileTemplate, SeedTemplate};
+use crate::types::Context;
+
+/// Data directory file the recovery phrase is written to in file mode.
+const SEED_FILE: &str = "recovery_phrase.txt";
+
+/// Seconds the phrase stays in the chat when it can't be written to a file.
+const FALLBACK_LIFETIME: u64 = 60;
+
+/// Reply for a new wallet's recovery phrase, and the seconds it may stay in the chat.
+pub fn deliver(context: &Context, seed: &str) -> (String, Option<u64>) {
+    match context.seed_delivery {
+        SeedDelivery::Ephemeral(lifetime) => {
+            let message = SeedTemplate { seed }.render().unwrap();
+            (message, Some(lifetime))
+        }
+        // The wallet already exists, so the phrase must not be lost if the file can't be written.
+        SeedDelivery::File => match write_file(&context.data_dir, seed) {
+            Ok(path) => {
+                let path = path.display().to_string();
+                let message = SeedFileTemplate { path: &path }.render().unwrap();
+                (message, None)
+            }
+            Err(e) => {
+                error!(
+                    "Can't write the recovery phrase to a file, sending it instead: {}",
+                    e
+                );
+                let lifetime = FALLBACK_LIFETIME;
+                let message = SeedTemplate { seed }.render().unwrap();
+                (message, Some(lifetime))
+            }
+        },
+    }
+}
+
+/// Writes the recovery phrase to a file only the bot's user can read. Never overwrites one.
+fn write_file(data_dir: &str, seed: &str) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(data_dir)?;
+    let path = Path::new(data_dir).join(SEED_FILE);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    writeln!(file, "{}", seed)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn seed_file_is_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = Context {
+            data_dir: dir.path().to_str().unwrap().to_string(),
+            seed_delivery: SeedDelivery::File,
+            ..Default::default()
+        };
+
+        let (message, lifetime) = deliver(&context, "word1 word2");
+        assert!(!message.contains("word1"));
+        assert_eq!(lifetime, None);
+        let path = dir.path().join(SEED_FILE);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "word1 word2\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // An existing phrase is never overwritten. The new one is sent instead.
+        let (message, lifetime) = deliver(&context, "word3 word4");
+        assert!(message.contains("word3 word4"));
+        assert_eq!(lifetime, Some(60));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "word1 word2\n");
+    }
+
+    #[test]
+    fn ephemeral_seed_message() {
+        let context = Context::default();
+        let (message, lifetime) = deliver(&context, "word1 word2");
+        assert!(message.contains("word1 word2"));
+        assert_eq!(lifetime, Some(60));
+    }
+}
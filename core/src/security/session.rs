@@ -0,0 +1,144 @@
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use rand::RngCore;
+
+use crate::controller::types::Action;
+use crate::security::types::Session;
+use crate::types::Context;
+
+/// Wrong PINs allowed before /unlock is refused for a while.
+const MAX_FAILURES: u32 = 5;
+
+/// Minutes /unlock is refused after too many wrong PINs.
+const LOCKOUT_MINUTES: i64 = 15;
+
+/// Whether an action is allowed while the session is locked.
+pub fn allowed_while_locked(action: &Action) -> bool {
+    match action {
+        Action::Help(_)
+        | Action::Unlock(..)
+        | Action::Lock(_)
+        | Action::Freeze(_)
+        | Action::Tick(_)
+        | Action::NoUsername(_)
+        | Action::WrongUsername(_)
+        | Action::NotPermitted(..)
+        | Action::ModeNotSupported(_) => true,
+        _ => false,
+    }
+}
+
+/// Keeps an unlocked session open for another idle period.
+pub fn touch(session: &mut Session, context: &Context, now: DateTime<Utc>) {
+    if !session.is_locked(now) {
+        session.unlocked_until = Some(now + Duration::minutes(context.lock_after as i64));
+    }
+}
+
+/// Checks a PIN against the argon2 hash from config.yml and unlocks the session.
+pub fn unlock(
+    session: &mut Session,
+    context: &Context,
+    pin: &str,
+    now: DateTime<Utc>,
+) -> Result<String, String> {
+    let pin_hash = match &context.pin_hash {
+        Some(pin_hash) => pin_hash,
+        None => return Ok("Grin Bot has no PIN lock.".to_string()),
+    };
+    if let Some(until) = session.locked_out_until {
+        if until > now {
+            return Err(format!(
+                "Too many wrong PINs. Try again after {}.",
+                until.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+        session.locked_out_until = None;
+    }
+
+    match argon2::verify_encoded(pin_hash, pin.as_bytes()) {
+        Ok(true) => {
+            session.failures = 0;
+            session.unlocked_until = Some(now + Duration::minutes(context.lock_after as i64));
+            Ok(format!(
+                "Unlocked. Grin Bot locks again after {} idle minutes, or with /lock.",
+                context.lock_after
+            ))
+        }
+        Ok(false) => {
+            session.failures += 1;
+            warn!("Wrong PIN ({} in a row)", session.failures);
+            if session.failures >= MAX_FAILURES {
+                session.failures = 0;
+                session.locked_out_until = Some(now + Duration::minutes(LOCKOUT_MINUTES));
+                return Err(format!(
+                    "Wrong PIN. /unlock is disabled for {} minutes.",
+                    LOCKOUT_MINUTES
+                ));
+            }
+            Err(format!(
+                "Wrong PIN. {} attempt(s) left.",
+                MAX_FAILURES - session.failures
+            ))
+        }
+        Err(e) => Err(format!(
+            "security.pin_hash in config.yml is not a valid argon2 hash: {}",
+            e
+        )),
+    }
+}
+
+/// Hashes a PIN with a random salt for pin_hash in config.yml.
+pub fn hash_pin(pin: &str) -> String {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    argon2::hash_encoded(pin.as_bytes(), &salt, &argon2::Config::default()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        Context {
+            pin_hash: Some(hash_pin("2468")),
+            lock_after: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unlock_and_idle_lock() {
+        let context = context();
+        let now = Utc::now();
+        let mut session = Session::default();
+        assert!(session.is_locked(now));
+
+        assert!(unlock(&mut session, &context, "2468", now).is_ok());
+        assert!(!session.is_locked(now + Duration::minutes(9)));
+        assert!(session.is_locked(now + Duration::minutes(10)));
+
+        // Activity keeps the session open.
+        touch(&mut session, &context, now + Duration::minutes(9));
+        assert!(!session.is_locked(now + Duration::minutes(18)));
+    }
+
+    #[test]
+    fn wrong_pins_lock_out() {
+        let context = context();
+        let now = Utc::now();
+        let mut session = Session::default();
+        for _ in 0..MAX_FAILURES {
+            assert!(unlock(&mut session, &context, "1111", now).is_err());
+        }
+        assert!(unlock(&mut session, &context, "2468", now).is_err());
+        assert!(session.is_locked(now));
+        assert!(unlock(
+            &mut session,
+            &context,
+            "2468",
+            now + Duration::minutes(LOCKOUT_MINUTES + 1)
+        )
+        .is_ok());
+    }
+}
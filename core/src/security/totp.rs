@@ -0,0 +1,168 @@
+use base32::Alphabet;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use log::warn;
+use rand::RngCore;
+use sha1::Sha1;
+
+use std::error::Error;
+
+use crate::security::types::{TotpError, TotpState};
+use crate::storage;
+use crate::types::Context;
+
+/// Data directory file with the last accepted code and failed attempts.
+const TOTP_STATE_FILE: &str = "totp.json";
+
+/// Seconds each code is valid for.
+const STEP: i64 = 30;
+
+/// Failed codes allowed before codes are locked out.
+const MAX_FAILURES: u32 = 5;
+
+/// Minutes codes are locked out after too many failures.
+const LOCKOUT_MINUTES: i64 = 15;
+
+const ALPHABET: Alphabet = Alphabet::RFC4648 { padding: false };
+
+/// Generates a random base32 secret for an authenticator app.
+pub fn generate_secret() -> String {
+    let mut key = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut key);
+    base32::encode(ALPHABET, &key)
+}
+
+/// Link that authenticator apps read from a QR code.
+pub fn otpauth_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/Grin%20Bot:{}?secret={}&issuer=Grin%20Bot&algorithm=SHA1&digits=6&period={}",
+        account, secret, STEP
+    )
+}
+
+/// HOTP value (RFC 4226) for a counter.
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_varkey(key).unwrap();
+    mac.input(&counter.to_be_bytes());
+    let hash = mac.result().code();
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = (u32::from(hash[offset]) & 0x7f) << 24
+        | u32::from(hash[offset + 1]) << 16
+        | u32::from(hash[offset + 2]) << 8
+        | u32::from(hash[offset + 3]);
+    binary % 1_000_000
+}
+
+/// Checks a code against the time steps around `now`. A step can only be used once,
+/// and too many failures lock out all codes for a while.
+pub fn verify(
+    secret: &str,
+    code: &str,
+    now: DateTime<Utc>,
+    state: &mut TotpState,
+) -> Result<(), TotpError> {
+    if let Some(until) = state.locked_until {
+        if until > now {
+            return Err(TotpError::LockedOut(until));
+        }
+        state.locked_until = None;
+    }
+
+    let key = base32::decode(ALPHABET, secret).ok_or(TotpError::BadSecret)?;
+    let code = code.parse::<u32>().ok();
+    let current = (now.timestamp() / STEP) as u64;
+
+    // Allow one step either side for clock drift.
+    for step in current.saturating_sub(1)..=current + 1 {
+        if code == Some(hotp(&key, step)) {
+            if let Some(last) = state.last_step {
+                if step <= last {
+                    return Err(TotpError::Replayed);
+                }
+            }
+            state.last_step = Some(step);
+            state.failures = 0;
+            return Ok(());
+        }
+    }
+
+    state.failures += 1;
+    if state.failures >= MAX_FAILURES {
+        state.failures = 0;
+        let until = now + Duration::minutes(LOCKOUT_MINUTES);
+        state.locked_until = Some(until);
+        return Err(TotpError::LockedOut(until));
+    }
+    Err(TotpError::WrongCode(MAX_FAILURES - state.failures))
+}
+
+/// Checks a code from chat against the configured secret.
+pub fn check(context: &Context, code: &str) -> Result<(), Box<dyn Error>> {
+    let secret = match &context.totp_secret {
+        Some(secret) => secret,
+        None => return Ok(()),
+    };
+    let mut state: TotpState = storage::load(&context.data_dir, TOTP_STATE_FILE)?;
+    let result = verify(secret, code, Utc::now(), &mut state);
+    storage::save(&context.data_dir, TOTP_STATE_FILE, &state)?;
+    if let Err(e) = result {
+        warn!("Rejected authenticator code: {}", e);
+        return Err(Box::new(e));
+    }
+    Ok(())
+}
+
+/// Whether a token looks like an authenticator code.
+pub fn is_code(token: &str) -> bool {
+    token.len() == 6 && token.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // RFC 6238 test secret "12345678901234567890".
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn rfc_6238_codes() {
+        let key = base32::decode(ALPHABET, SECRET).unwrap();
+        assert_eq!(hotp(&key, 59 / 30), 287_082);
+        assert_eq!(hotp(&key, 1_111_111_109 / 30), 81_804);
+        assert_eq!(hotp(&key, 2_000_000_000 / 30), 279_037);
+    }
+
+    #[test]
+    fn codes_are_single_use() {
+        let now = Utc.timestamp(59, 0);
+        let mut state = TotpState::default();
+        assert_eq!(verify(SECRET, "287082", now, &mut state), Ok(()));
+        assert_eq!(
+            verify(SECRET, "287082", now, &mut state),
+            Err(TotpError::Replayed)
+        );
+    }
+
+    #[test]
+    fn lockout_after_failures() {
+        let now = Utc.timestamp(59, 0);
+        let mut state = TotpState::default();
+        for attempts_left in (1..MAX_FAILURES).rev() {
+            assert_eq!(
+                verify(SECRET, "000000", now, &mut state),
+                Err(TotpError::WrongCode(attempts_left))
+            );
+        }
+        let until = now + Duration::minutes(LOCKOUT_MINUTES);
+        assert_eq!(
+            verify(SECRET, "000000", now, &mut state),
+            Err(TotpError::LockedOut(until))
+        );
+        // Even the right code is refused until the lockout ends.
+        assert_eq!(
+            verify(SECRET, "287082", now, &mut state),
+            Err(TotpError::LockedOut(until))
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::{error::Error, fmt};
+
+/// Maximum amounts that may be sent, from config.yml. None means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpendingLimits {
+    pub per_tx: Option<f64>,
+    pub per_day: Option<f64>,
+    pub per_week: Option<f64>,
+}
+
+/// A send, recorded before it's made so it counts against the limits.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Spend {
+    pub time: DateTime<Utc>,
+    pub amount: f64,
+    /// Not yet confirmed as sent. Still counts, since the send may have gone through.
+    #[serde(default)]
+    pub pending: bool,
+}
+
+/// Sends in the last week, used for the rolling limits.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct SpendHistory {
+    pub spends: Vec<Spend>,
+}
+
+/// The limit a send would exceed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    PerTx,
+    PerDay,
+    PerWeek,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::PerTx => write!(f, "per transaction"),
+            Limit::PerDay => write!(f, "per 24 hours"),
+            Limit::PerWeek => write!(f, "per 7 days"),
+        }
+    }
+}
+
+/// Send refused because it would exceed a spending limit.
+#[derive(Debug, PartialEq)]
+pub struct LimitExceededError {
+    pub limit: Limit,
+    pub max: f64,
+    pub amount: f64,
+    /// Amount already sent in the limit's period.
+    pub spent: f64,
+}
+
+impl Error for LimitExceededError {}
+
+impl fmt::Display for LimitExceededError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.limit {
+            Limit::PerTx => write!(
+                f,
+                "Send refused: {} is over the limit of {} {}",
+                self.amount, self.max, self.limit
+            ),
+            _ => write!(
+                f,
+                "Send refused: {} would go over the limit of {} {} ({} already sent)",
+                self.amount, self.max, self.limit, self.spent
+            ),
+        }
+    }
+}
+
+/// Replay and lockout state for authenticator codes.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TotpState {
+    /// Time step of the last accepted code. Codes from it or earlier are refused.
+    pub last_step: Option<u64>,
+    pub failures: u32,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Authenticator code was not accepted.
+#[derive(Debug, PartialEq)]
+pub enum TotpError {
+    WrongCode(u32),
+    Replayed,
+    LockedOut(DateTime<Utc>),
+    BadSecret,
+}
+
+impl Error for TotpError {}
+
+impl fmt::Display for TotpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TotpError::WrongCode(attempts_left) => write!(
+                f,
+                "Wrong authenticator code. {} attempt(s) left before codes are locked",
+                attempts_left
+            ),
+            TotpError::Replayed => {
+                write!(f, "Authenticator code already used. Wait for the next code")
+            }
+            TotpError::LockedOut(until) => write!(
+                f,
+                "Too many wrong authenticator codes. Codes are locked until {}",
+                until.format("%Y-%m-%d %H:%M UTC")
+            ),
+            TotpError::BadSecret => {
+                write!(f, "security.totp_secret in config.yml is not valid base32")
+            }
+        }
+    }
+}
+
+/// PIN lock of the chat session. Locked until /unlock, and again after idle minutes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Session {
+    /// End of the unlocked period. None when locked.
+    pub unlocked_until: Option<DateTime<Utc>>,
+    pub failures: u32,
+    pub locked_out_until: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    /// Session for commands run on the host with -c, which don't need the PIN.
+    pub fn local() -> Self {
+        Session {
+            unlocked_until: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        match self.unlocked_until {
+            Some(until) => until <= now,
+            None => true,
+        }
+    }
+}
+
+/// Kill switch set with /freeze. Only `grinbot unfreeze` on the host clears it.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct FreezeState {
+    /// When the wallet was frozen. None when not frozen.
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Send refused because the wallet is frozen.
+#[derive(Debug, PartialEq)]
+pub struct FrozenError(pub DateTime<Utc>);
+
+impl Error for FrozenError {}
+
+impl fmt::Display for FrozenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Grin Bot is frozen since {}. Spending is disabled until grinbot unfreeze is run on the host",
+            self.0.format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+}
+
+/// What a user may do. Each role may also do everything the roles before it may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Balances, lists and help.
+    Viewer,
+    /// Sends, confirms, cancels and notification settings.
+    Spender,
+    /// Wallet creation, schedules and contacts.
+    Admin,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "viewer" => Some(Role::Viewer),
+            "spender" => Some(Role::Spender),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::Viewer => write!(f, "viewer"),
+            Role::Spender => write!(f, "spender"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Send refused because read_only is set in config.yml.
+#[derive(Debug, PartialEq)]
+pub struct ReadOnlyError;
+
+impl Error for ReadOnlyError {}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Grin Bot is read-only. Only viewing commands are allowed"
+        )
+    }
+}
+
+/// How the recovery phrase from /create reaches the user, from config.yml.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeedDelivery {
+    /// In a chat message removed after this many seconds.
+    Ephemeral(u64),
+    /// Only written to a file in data_dir on the host, never sent.
+    File,
+}
+
+impl Default for SeedDelivery {
+    fn default() -> Self {
+        SeedDelivery::Ephemeral(60)
+    }
+}
+
+/// Recovery phrase backup state, kept between restarts. The phrase itself is never stored.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct BackupState {
+    /// A wallet was created with /create and its backup isn't verified yet.
+    pub pending: bool,
+}
+
+/// Words of a new wallet's recovery phrase, held in memory until /backup verifies them.
+#[derive(Clone, PartialEq)]
+pub struct BackupQuiz {
+    pub words: Vec<String>,
+    /// Zero-based positions of the words asked for, in order.
+    pub positions: Vec<usize>,
+}
+
+/// Never print the phrase, even in debug logs.
+impl fmt::Debug for BackupQuiz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BackupQuiz {{ positions: {:?} }}", self.positions)
+    }
+}
+
+/// What /send does until the recovery phrase backup is verified, from config.yml.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnverifiedBackup {
+    Remind,
+    Block,
+}
+
+impl Default for UnverifiedBackup {
+    fn default() -> Self {
+        UnverifiedBackup::Remind
+    }
+}
+
+/// Send refused because the recovery phrase backup isn't verified.
+#[derive(Debug, PartialEq)]
+pub struct BackupNotVerifiedError;
+
+impl Error for BackupNotVerifiedError {}
+
+impl fmt::Display for BackupNotVerifiedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Send refused: verify your recovery phrase backup with /backup first"
+        )
+    }
+}
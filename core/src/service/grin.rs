@@ -2,39 +2,46 @@ use askama::Template;
 use reqwest::Client;
 use serde_json::json;
 
+use chrono::{DateTime, TimeZone, Utc};
+
 use std::error::Error;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
-use std::process::{Child, Command};
+use std::sync::atomic::{AtomicI64, Ordering};
 
-use grin_wallet_libwallet::{InitTxArgs, InitTxSendArgs, WalletInfo};
+use grin_wallet_libwallet::{InitTxArgs, InitTxSendArgs, TxLogEntry, WalletInfo};
 
+use crate::service::secure::SecureSession;
 use crate::service::types::WalletInfoGrin;
 use crate::service::types::{
-    ApiSecretMissingError, Args, CreateWalletError, GrinAmount, MaybeReply, NanoGrinAmount,
-    RpcRequest, RpcResponse, WalletExistsError,
+    ApiSecretMissingError, Args, CreateWalletError, GrinAmount, MaybeReply, MnemonicLength,
+    NanoGrinAmount, OwnerApiError, RpcRequest, RpcResponse, StoredTxMissingError, TxNotFoundError,
+    WalletExistsError,
 };
 use crate::template::templates::{InfoSuccessTemplate, SendSuccessTemplate};
 
-/// Sends Grin.
+/// Unix time of the last successful owner API call, 0 before the first.
+static LAST_SUCCESS: AtomicI64 = AtomicI64::new(0);
+
+/// Time of the last successful owner API call since the bot started.
+pub fn last_success() -> Option<DateTime<Utc>> {
+    match LAST_SUCCESS.load(Ordering::SeqCst) {
+        0 => None,
+        secs => Utc.timestamp_opt(secs, 0).single(),
+    }
+}
+
+/// Sends Grin. A payment proof is requested when the recipient's proof address is known.
 pub fn send(
     amount: GrinAmount,
     dest: &str,
+    proof_address: Option<&str>,
     wallet_dir: &str,
     owner_endpoint: &str,
     client: &Client,
 ) -> Result<String, Box<dyn Error>> {
     let ita = InitTxArgs {
-        src_acct_name: Some("default".into()),
-        amount: amount.as_nano_grin() as u64, // Conversion occurs here
-        minimum_confirmations: 10,
-        max_outputs: 500,
-        num_change_outputs: 1,
-        selection_strategy_is_use_all: false,
-        message: None,
-        target_slate_version: None,
-        estimate_only: None,
         send_args: Some(InitTxSendArgs {
             method: "http".into(),
             dest: dest.to_string(),
@@ -42,13 +49,15 @@ pub fn send(
             post_tx: true,
             fluff: false,
         }),
-        payment_proof_recipient_address: None,
-        ttl_blocks: None,
+        ..init_tx_args(amount)
     };
 
-    let args = Args {
-        args: Some(serde_json::to_value(&ita).unwrap()),
-    };
+    let mut ita = serde_json::to_value(&ita).unwrap();
+    if let Some(address) = proof_address {
+        ita["payment_proof_recipient_address"] = json!(address);
+    }
+
+    let args = Args { args: Some(ita) };
 
     let rpc_request = RpcRequest {
         id: "1".to_owned(),
@@ -67,6 +76,7 @@ pub fn send(
     let response: RpcResponse = raw_response.json()?;
     match response.result {
         MaybeReply::Ok(rpc) => {
+            LAST_SUCCESS.store(Utc::now().timestamp(), Ordering::SeqCst);
             let amount =
                 NanoGrinAmount::new(rpc["amount"].as_str().unwrap().parse::<f64>()?).as_grin();
 
@@ -88,19 +98,199 @@ pub fn send(
     }
 }
 
+/// Estimates the fee of a send without locking any outputs.
+pub fn estimate_fee(
+    amount: GrinAmount,
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<f64, Box<dyn Error>> {
+    let ita = InitTxArgs {
+        estimate_only: Some(true),
+        ..init_tx_args(amount)
+    };
+    let slate = owner_rpc(
+        "init_send_tx",
+        json!({ "args": ita }),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    let fee = slate["fee"]
+        .as_str()
+        .ok_or_else(|| OwnerApiError("estimate has no fee".to_string()))?;
+    Ok(NanoGrinAmount::new(fee.parse::<f64>()?).as_grin())
+}
+
+/// Transaction arguments shared by sends and estimates.
+fn init_tx_args(amount: GrinAmount) -> InitTxArgs {
+    InitTxArgs {
+        src_acct_name: Some("default".into()),
+        amount: amount.as_nano_grin() as u64, // Conversion occurs here
+        minimum_confirmations: 10,
+        max_outputs: 500,
+        num_change_outputs: 1,
+        selection_strategy_is_use_all: false,
+        message: None,
+        target_slate_version: None,
+        estimate_only: None,
+        send_args: None,
+        payment_proof_recipient_address: None,
+        ttl_blocks: None,
+    }
+}
+
 /// Gets balance.
 pub fn balance(
     wallet_dir: &str,
     owner_endpoint: &str,
     client: &Client,
 ) -> Result<String, Box<dyn Error>> {
-    let ita = json!([true, 10]);
+    let info = wallet_info(wallet_dir, owner_endpoint, client)?;
+    let message = InfoSuccessTemplate { info }.render().unwrap();
+    Ok(message)
+}
+
+/// Gets wallet summary info, refreshed from the node.
+pub fn wallet_info(
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<WalletInfoGrin, Box<dyn Error>> {
+    let rpc = owner_rpc(
+        "retrieve_summary_info",
+        json!([true, 10]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    let nano_info: WalletInfo = serde_json::from_value(rpc[1].clone())?;
+    Ok(WalletInfoGrin::new(nano_info))
+}
 
+/// Gets the address senders give to request a payment proof from this wallet.
+pub fn proof_address(
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<String, Box<dyn Error>> {
+    let rpc = owner_rpc(
+        "get_public_proof_address",
+        json!([0]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    match rpc.as_str() {
+        Some(address) => Ok(address.to_string()),
+        None => Err(Box::new(OwnerApiError(format!(
+            "unexpected proof address {}",
+            rpc
+        )))),
+    }
+}
+
+/// Gets the chain height as the wallet's node sees it, and whether
+/// the wallet could reach the node to get it.
+pub fn node_height(
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<(u64, bool), Box<dyn Error>> {
+    let rpc = owner_rpc("node_height", json!([]), wallet_dir, owner_endpoint, client)?;
+    let height = match &rpc["height"] {
+        serde_json::Value::String(height) => height.parse::<u64>()?,
+        height => height
+            .as_u64()
+            .ok_or_else(|| OwnerApiError(format!("unexpected node height {}", rpc)))?,
+    };
+    Ok((height, rpc["updated_from_node"].as_bool().unwrap_or(false)))
+}
+
+/// Cancels a transaction and unlocks its outputs.
+pub fn cancel_tx(
+    tx_id: u32,
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<String, Box<dyn Error>> {
+    owner_rpc(
+        "cancel_tx",
+        json!([tx_id, null]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    Ok(format!("Transaction {} cancelled.", tx_id))
+}
+
+/// Posts a transaction's stored copy to the node again.
+pub fn repost_tx(
+    tx_id: u32,
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<String, Box<dyn Error>> {
+    let rpc = owner_rpc(
+        "retrieve_txs",
+        json!([true, tx_id, null]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    let txs: Vec<TxLogEntry> = serde_json::from_value(rpc[1].clone())?;
+    let tx = txs.first().ok_or(TxNotFoundError(tx_id))?;
+
+    let stored_tx = owner_rpc(
+        "get_stored_tx",
+        json!([tx]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    if stored_tx.is_null() {
+        return Err(Box::new(StoredTxMissingError(tx_id)));
+    }
+
+    owner_rpc(
+        "post_tx",
+        json!([stored_tx, false]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    Ok(format!("Transaction {} reposted.", tx_id))
+}
+
+/// Gets the wallet's transaction log, refreshed from the node.
+pub fn retrieve_txs(
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<Vec<TxLogEntry>, Box<dyn Error>> {
+    let rpc = owner_rpc(
+        "retrieve_txs",
+        json!([true, null, null]),
+        wallet_dir,
+        owner_endpoint,
+        client,
+    )?;
+    Ok(serde_json::from_value(rpc[1].clone())?)
+}
+
+/// Calls an owner API method and returns the result of a successful reply.
+pub(crate) fn owner_rpc(
+    method: &str,
+    params: serde_json::Value,
+    wallet_dir: &str,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<serde_json::Value, Box<dyn Error>> {
     let rpc_request = RpcRequest {
         id: "1".to_owned(),
         jsonrpc: "2.0".to_owned(),
-        method: "retrieve_summary_info".to_owned(),
-        params: Some(ita),
+        method: method.to_owned(),
+        params: Some(params),
     };
 
     let api_secret = get_api_secret(wallet_dir)?;
@@ -113,39 +303,69 @@ pub fn balance(
     let response: RpcResponse = raw_response.json()?;
     match response.result {
         MaybeReply::Ok(rpc) => {
-            let nano_info: WalletInfo = serde_json::from_value(rpc[1].clone()).unwrap();
-            let info = WalletInfoGrin::new(nano_info);
-
-            let message = InfoSuccessTemplate { info }.render().unwrap();
-            Ok(message)
+            LAST_SUCCESS.store(Utc::now().timestamp(), Ordering::SeqCst);
+            Ok(rpc)
         }
-        MaybeReply::Err(e) => Ok(serde_json::to_string_pretty(&e)?),
+        MaybeReply::Err(e) => Err(Box::new(OwnerApiError(e.to_string()))),
     }
 }
 
-/// Generates new wallet and returns the seed.
-pub fn new_wallet(wallet_dir: &str, password: &str) -> Result<String, Box<dyn Error>> {
-    let your_recovery_phrase = "Your recovery phrase is:";
-
-    let path = Path::new(&wallet_dir);
-    if !Path::exists(path) {
-        Command::new("mkdir").arg(wallet_dir).output()?;
-
-        let output = Command::new("grin-wallet")
-            .current_dir(wallet_dir)
-            .args(&["-p", password, "init", "-h"])
-            .output()?;
-
-        let utf8_output = String::from_utf8_lossy(&output.stdout).to_string();
-        let lines: Vec<&str> = utf8_output.split("\n").collect();
-        let seed_index = lines.iter().position(|&r| r == your_recovery_phrase);
-        if let Some(i) = seed_index {
-            Ok(lines[i + 2].to_string())
-        } else {
-            Err(Box::new(CreateWalletError))
-        }
-    } else {
-        Err(Box::new(WalletExistsError))
+/// Creates a wallet through the owner API and returns the recovery phrase.
+///
+/// The owner API must be running with wallet_dir as its top level directory.
+/// The password only travels over the encrypted v3 channel, never on a command line.
+pub fn new_wallet(
+    wallet_dir: &str,
+    password: &str,
+    length: MnemonicLength,
+    owner_endpoint: &str,
+    client: &Client,
+) -> Result<String, Box<dyn Error>> {
+    if Path::new(wallet_dir).join("wallet_data").exists() {
+        return Err(Box::new(WalletExistsError));
+    }
+    let session = SecureSession::init(wallet_dir, owner_endpoint, client)?;
+
+    session
+        .call(
+            "create_config",
+            json!({
+                "chain_type": "Mainnet",
+                "wallet_config": null,
+                "logging_config": null,
+                "tor_config": null,
+            }),
+            wallet_dir,
+            client,
+        )
+        .map_err(|e| CreateWalletError::Config(e.to_string()))?;
+    session
+        .call(
+            "create_wallet",
+            json!({
+                "name": null,
+                "mnemonic": null,
+                "mnemonic_length": length.entropy_bytes(),
+                "password": password,
+            }),
+            wallet_dir,
+            client,
+        )
+        .map_err(|e| CreateWalletError::Wallet(e.to_string()))?;
+    let mnemonic = session
+        .call(
+            "get_mnemonic",
+            json!({ "name": null, "password": password }),
+            wallet_dir,
+            client,
+        )
+        .map_err(|e| CreateWalletError::Mnemonic(e.to_string()))?;
+
+    match mnemonic.as_str() {
+        Some(seed) => Ok(seed.to_string()),
+        None => Err(Box::new(CreateWalletError::Mnemonic(
+            "reply is not a phrase".to_string(),
+        ))),
     }
 }
 
@@ -162,12 +382,3 @@ pub fn get_api_secret(wallet_dir: &str) -> Result<String, Box<dyn Error>> {
         Err(Box::new(ApiSecretMissingError))
     }
 }
-
-#[allow(dead_code)]
-/// Unused start owner API function. For future development.
-pub fn start_owner_api(wallet_dir: &str, password: &str) -> Result<Child, std::io::Error> {
-    Command::new("grin-wallet")
-        .current_dir(wallet_dir)
-        .args(&["-p", password, "owner_api"])
-        .spawn()
-}
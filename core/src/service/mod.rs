@@ -3,5 +3,9 @@
 
 /// Services associated with Grin and the Grin Wallet.
 pub mod grin;
+/// The Grin node API.
+pub mod node;
+/// Encrypted owner API v3 calls.
+pub mod secure;
 /// Types for external services.
 pub mod types;
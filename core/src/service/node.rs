@@ -0,0 +1,40 @@
+use reqwest::Client;
+use serde_json::json;
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::service::types::{MaybeReply, NodeStatus, OwnerApiError, RpcRequest, RpcResponse};
+
+/// Gets the node's height and sync state from its owner API.
+/// Uses the .api_secret in the node directory when there is one.
+pub fn status(
+    node_api: &str,
+    node_dir: &str,
+    client: &Client,
+) -> Result<NodeStatus, Box<dyn Error>> {
+    let rpc_request = RpcRequest {
+        id: "1".to_owned(),
+        jsonrpc: "2.0".to_owned(),
+        method: "get_status".to_owned(),
+        params: Some(json!([])),
+    };
+
+    let mut request = client
+        .post(&format!("{}/v2/owner", node_api.trim_end_matches('/')))
+        .json(&rpc_request);
+    if let Ok(secret) = fs::read_to_string(Path::new(node_dir).join(".api_secret")) {
+        request = request.basic_auth("grin", Some(secret.trim().to_string()));
+    }
+    let response: RpcResponse = request.send()?.json()?;
+
+    match response.result {
+        MaybeReply::Ok(rpc) => Ok(NodeStatus {
+            height: rpc["tip"]["height"].as_u64().unwrap_or(0),
+            sync_status: rpc["sync_status"].as_str().unwrap_or("unknown").to_string(),
+            highest_height: rpc["sync_info"]["highest_height"].as_u64(),
+        }),
+        MaybeReply::Err(e) => Err(Box::new(OwnerApiError(e.to_string()))),
+    }
+}
@@ -0,0 +1,173 @@
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::Aes256Gcm;
+use rand::RngCore;
+use reqwest::Client;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde_json::json;
+
+use std::error::Error;
+
+use crate::service::grin;
+use crate::service::types::{MaybeReply, OwnerApiError, RpcRequest, RpcResponse, SecureApiError};
+
+/// An encrypted session with the owner API v3. Methods only in v3, like wallet
+/// creation, must be sent through one.
+pub struct SecureSession {
+    /// AES-256-GCM key agreed with the wallet by ECDH.
+    key: [u8; 32],
+    /// The v3 endpoint derived from owner_endpoint in config.yml.
+    endpoint: String,
+}
+
+impl SecureSession {
+    /// Agrees on a shared key with the owner API.
+    pub fn init(
+        wallet_dir: &str,
+        owner_endpoint: &str,
+        client: &Client,
+    ) -> Result<Self, Box<dyn Error>> {
+        let secp = Secp256k1::new();
+        let secret_key = random_secret_key()?;
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+
+        let endpoint = v3_endpoint(owner_endpoint);
+        let reply = grin::owner_rpc(
+            "init_secure_api",
+            json!({ "ecdh_pubkey": hex::encode(&public_key.serialize()[..]) }),
+            wallet_dir,
+            &endpoint,
+            client,
+        )?;
+        let wallet_key = reply
+            .as_str()
+            .and_then(|key| hex::decode(key).ok())
+            .and_then(|key| PublicKey::from_slice(&key).ok())
+            .ok_or_else(|| SecureApiError(format!("unexpected public key {}", reply)))?;
+
+        Ok(SecureSession {
+            key: shared_key(&wallet_key, &secret_key)?,
+            endpoint,
+        })
+    }
+
+    /// Calls an owner API method through the encrypted channel and returns the
+    /// result of a successful reply.
+    pub fn call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        wallet_dir: &str,
+        client: &Client,
+    ) -> Result<serde_json::Value, Box<dyn Error>> {
+        let rpc_request = RpcRequest {
+            id: "1".to_owned(),
+            jsonrpc: "2.0".to_owned(),
+            method: method.to_owned(),
+            params: Some(params),
+        };
+        let body = encrypt(&self.key, &serde_json::to_vec(&rpc_request)?)?;
+        let reply = grin::owner_rpc(
+            "encrypted_request_v3",
+            body,
+            wallet_dir,
+            &self.endpoint,
+            client,
+        )?;
+
+        let response: RpcResponse = serde_json::from_slice(&decrypt(&self.key, &reply)?)?;
+        match response.result {
+            MaybeReply::Ok(rpc) => Ok(rpc),
+            MaybeReply::Err(e) => Err(Box::new(OwnerApiError(e.to_string()))),
+        }
+    }
+}
+
+/// The owner API v3 endpoint for a v2 one, e.g. http://127.0.0.1:3420/v3/owner.
+pub fn v3_endpoint(owner_endpoint: &str) -> String {
+    owner_endpoint.replace("/v2/owner", "/v3/owner")
+}
+
+/// A new random secp256k1 secret key.
+fn random_secret_key() -> Result<SecretKey, SecureApiError> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    SecretKey::from_slice(&bytes).map_err(|e| SecureApiError(e.to_string()))
+}
+
+/// The x coordinate of the ECDH point, as grin-wallet derives it.
+fn shared_key(public_key: &PublicKey, secret_key: &SecretKey) -> Result<[u8; 32], SecureApiError> {
+    let secp = Secp256k1::new();
+    let mut point = *public_key;
+    point
+        .mul_assign(&secp, &secret_key[..])
+        .map_err(|e| SecureApiError(e.to_string()))?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&point.serialize()[1..]);
+    Ok(key)
+}
+
+/// Encrypts a request body into encrypted_request_v3 params.
+fn encrypt(key: &[u8; 32], body: &[u8]) -> Result<serde_json::Value, SecureApiError> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let cipher = Aes256Gcm::new(&(*key).into());
+    let body_enc = cipher
+        .encrypt(&nonce.into(), body)
+        .map_err(|_| SecureApiError("can't encrypt request".to_string()))?;
+    Ok(json!({
+        "nonce": hex::encode(nonce),
+        "body_enc": base64::encode(&body_enc),
+    }))
+}
+
+/// Decrypts the result of an encrypted_request_v3 reply.
+fn decrypt(key: &[u8; 32], reply: &serde_json::Value) -> Result<Vec<u8>, SecureApiError> {
+    let mut nonce = [0u8; 12];
+    match reply["nonce"]
+        .as_str()
+        .and_then(|nonce| hex::decode(nonce).ok())
+    {
+        Some(ref bytes) if bytes.len() == nonce.len() => nonce.copy_from_slice(bytes),
+        _ => return Err(SecureApiError("reply has no valid nonce".to_string())),
+    }
+    let body_enc = reply["body_enc"]
+        .as_str()
+        .and_then(|body| base64::decode(body).ok())
+        .ok_or_else(|| SecureApiError("reply has no valid body".to_string()))?;
+    let cipher = Aes256Gcm::new(&(*key).into());
+    cipher
+        .decrypt(&nonce.into(), body_enc.as_slice())
+        .map_err(|_| SecureApiError("can't decrypt reply, the shared key is wrong".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_agree() {
+        let secp = Secp256k1::new();
+        let ours = random_secret_key().unwrap();
+        let theirs = random_secret_key().unwrap();
+        let key = shared_key(&PublicKey::from_secret_key(&secp, &theirs), &ours).unwrap();
+        assert_eq!(
+            key,
+            shared_key(&PublicKey::from_secret_key(&secp, &ours), &theirs).unwrap()
+        );
+
+        let body = encrypt(&key, b"{\"method\":\"get_mnemonic\"}").unwrap();
+        assert_eq!(
+            decrypt(&key, &body).unwrap(),
+            b"{\"method\":\"get_mnemonic\"}".to_vec()
+        );
+        assert!(decrypt(&[0u8; 32], &body).is_err());
+    }
+
+    #[test]
+    fn v3_endpoint_from_config() {
+        assert_eq!(
+            v3_endpoint("http://127.0.0.1:3420/v2/owner"),
+            "http://127.0.0.1:3420/v3/owner"
+        );
+    }
+}
@@ -29,15 +29,94 @@ impl fmt::Display for ApiSecretMissingError {
     }
 }
 
-/// Error creating wallet.
+/// Error creating wallet, by the owner API call that failed.
 #[derive(Debug)]
-pub struct CreateWalletError;
+pub enum CreateWalletError {
+    /// create_config: the wallet's grin-wallet.toml couldn't be written.
+    Config(String),
+    /// create_wallet: the seed couldn't be generated or stored.
+    Wallet(String),
+    /// get_mnemonic: the wallet exists but its recovery phrase couldn't be read.
+    Mnemonic(String),
+}
 
 impl Error for CreateWalletError {}
 
 impl fmt::Display for CreateWalletError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Can't create wallet")
+        match self {
+            CreateWalletError::Config(e) => write!(f, "Can't create wallet config: {}", e),
+            CreateWalletError::Wallet(e) => write!(f, "Can't create wallet: {}", e),
+            CreateWalletError::Mnemonic(e) => write!(
+                f,
+                "Wallet created, but its recovery phrase can't be read: {}",
+                e
+            ),
+        }
+    }
+}
+
+/// Error setting up or using the encrypted owner API v3 channel.
+#[derive(Debug)]
+pub struct SecureApiError(pub String);
+
+impl Error for SecureApiError {}
+
+impl fmt::Display for SecureApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Owner API encryption error: {}", self.0)
+    }
+}
+
+/// Unsupported number of recovery phrase words.
+#[derive(Debug, PartialEq)]
+pub struct MnemonicLengthError(pub u8);
+
+impl Error for MnemonicLengthError {}
+
+impl fmt::Display for MnemonicLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "A recovery phrase has 12, 15, 18, 21 or 24 words, not {}",
+            self.0
+        )
+    }
+}
+
+/// Transaction is not in the wallet's transaction log.
+#[derive(Debug)]
+pub struct TxNotFoundError(pub u32);
+
+impl Error for TxNotFoundError {}
+
+impl fmt::Display for TxNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transaction {} not found", self.0)
+    }
+}
+
+/// Transaction has no stored copy to repost.
+#[derive(Debug)]
+pub struct StoredTxMissingError(pub u32);
+
+impl Error for StoredTxMissingError {}
+
+impl fmt::Display for StoredTxMissingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No stored transaction to repost for {}", self.0)
+    }
+}
+
+/// Error reply from the Grin wallet owner API.
+#[derive(Debug)]
+pub struct OwnerApiError(pub String);
+
+impl Error for OwnerApiError {}
+
+impl fmt::Display for OwnerApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Owner API error: {}", self.0)
     }
 }
 
@@ -79,6 +158,50 @@ pub struct Args {
     pub args: Option<Value>,
 }
 
+/// Height and sync state of a Grin node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStatus {
+    pub height: u64,
+    /// no_sync when synced, otherwise the sync stage, e.g. header_sync.
+    pub sync_status: String,
+    /// Height of the best peer while syncing.
+    pub highest_height: Option<u64>,
+}
+
+impl NodeStatus {
+    pub fn synced(&self) -> bool {
+        self.sync_status == "no_sync"
+    }
+}
+
+/// Number of words in a new wallet's recovery phrase.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MnemonicLength(u8);
+
+impl MnemonicLength {
+    pub fn new(words: u8) -> Result<Self, MnemonicLengthError> {
+        match words {
+            12 | 15 | 18 | 21 | 24 => Ok(MnemonicLength(words)),
+            _ => Err(MnemonicLengthError(words)),
+        }
+    }
+
+    pub fn words(&self) -> u8 {
+        self.0
+    }
+
+    /// Bytes of seed entropy, the length create_wallet takes.
+    pub fn entropy_bytes(&self) -> u32 {
+        self.0 as u32 * 4 / 3
+    }
+}
+
+impl Default for MnemonicLength {
+    fn default() -> Self {
+        MnemonicLength(24)
+    }
+}
+
 /// An amount of whole Grin.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct GrinAmount {
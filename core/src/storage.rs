@@ -0,0 +1,30 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Loads a JSON file from the data directory.
+/// Returns the default value if the file has not been written yet.
+pub fn load<T: DeserializeOwned + Default>(
+    data_dir: &str,
+    name: &str,
+) -> Result<T, Box<dyn Error>> {
+    let path = Path::new(data_dir).join(name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Saves a value as JSON in the data directory.
+/// Writes to a temporary file first so an interrupted write can't corrupt the old file.
+pub fn save<T: Serialize>(data_dir: &str, name: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(data_dir)?;
+    let path = Path::new(data_dir).join(name);
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
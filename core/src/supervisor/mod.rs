@@ -0,0 +1,11 @@
+//! Processes Grin Bot runs alongside itself
+//!
+
+/// A local grin node.
+pub mod node;
+/// The grin-wallet owner API.
+pub mod owner_api;
+/// Keeps a child process running and logs its output.
+pub mod process;
+/// Types for supervised processes.
+pub mod types;
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use crate::supervisor::process::{self, Supervisor};
+use crate::supervisor::types::{ChildSpec, Events, NodeOptions, ProcessEvent};
+
+/// Starts grin server in the node directory, keeps it running, and queues its
+/// starts and crashes for the chat.
+pub fn start(options: &NodeOptions, events: &Events) -> Supervisor {
+    let api = options.api.clone();
+    let events = events.clone();
+    Supervisor::start(
+        spec(options),
+        Duration::from_secs(options.health_interval),
+        move || process::accepts_connections(&api),
+        move |event| events.push(message(&event)),
+    )
+}
+
+/// The node command. Set run_tui = false in grin-server.toml, a child has no terminal.
+pub fn spec(options: &NodeOptions) -> ChildSpec {
+    ChildSpec {
+        name: "grin_node".to_string(),
+        program: options.program.clone(),
+        args: vec!["server".to_string(), "run".to_string()],
+        current_dir: options.dir.clone(),
+        stdin: None,
+    }
+}
+
+/// Chat message for a node event.
+fn message(event: &ProcessEvent) -> String {
+    match event {
+        ProcessEvent::Started => "Grin node started.".to_string(),
+        ProcessEvent::Exited(status) => {
+            format!("Grin node crashed ({}). Restarting it.", status)
+        }
+        ProcessEvent::Unresponsive => {
+            "Grin node stopped answering its API. Restarting it.".to_string()
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use crate::supervisor::process::{self, Supervisor};
+use crate::supervisor::types::{ChildSpec, OwnerApiOptions};
+use crate::types::Context;
+
+/// Starts grin-wallet owner_api in wallet_dir and keeps it running.
+pub fn start(context: &Context, options: &OwnerApiOptions) -> Supervisor {
+    let owner_endpoint = context.owner_endpoint.clone();
+    Supervisor::start(
+        spec(context, options),
+        Duration::from_secs(options.health_interval),
+        move || process::accepts_connections(&owner_endpoint),
+        |_| {},
+    )
+}
+
+/// The owner_api command. The password is answered on stdin, never an argument,
+/// since arguments show in the process list.
+pub fn spec(context: &Context, options: &OwnerApiOptions) -> ChildSpec {
+    ChildSpec {
+        name: "owner_api".to_string(),
+        program: options.program.clone(),
+        args: vec!["owner_api".to_string()],
+        current_dir: context.wallet_dir.clone(),
+        stdin: Some(context.wallet_password.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_not_in_args() {
+        let context = Context {
+            wallet_dir: "/mywallet".to_string(),
+            wallet_password: "change_me".to_string(),
+            ..Default::default()
+        };
+        let options = OwnerApiOptions::default();
+        let spec = spec(&context, &options);
+        assert_eq!(spec.args, vec!["owner_api".to_string()]);
+        assert_eq!(spec.stdin, Some("change_me".to_string()));
+        assert!(!format!("{:?}", spec).contains("change_me"));
+    }
+}
@@ -0,0 +1,426 @@
+use log::{error, info, log, warn, Level};
+use url::Url;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::supervisor::types::{Backoff, ChildSpec, ProcessEvent};
+
+/// Failed health checks in a row before a running child is restarted.
+const MAX_FAILED_CHECKS: u32 = 3;
+
+/// Time a child must stay healthy before the restart delay starts over.
+const STABLE_AFTER: Duration = Duration::from_secs(120);
+
+/// Time a child gets to exit after SIGTERM before it's killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time a health check waits to connect.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Time as supervision sees it, so tests can run restarts without waiting for them.
+trait Clock: Send + 'static {
+    fn now(&self) -> Instant;
+
+    /// Sleeps, waking early if supervision stops. Returns whether it stopped.
+    fn sleep(&self, duration: Duration, stop: &AtomicBool) -> bool;
+}
+
+/// The system clock.
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration, stop: &AtomicBool) -> bool {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            if stop.load(Ordering::SeqCst) {
+                return true;
+            }
+            thread::sleep(std::cmp::min(
+                Duration::from_millis(100),
+                deadline - Instant::now(),
+            ));
+        }
+        stop.load(Ordering::SeqCst)
+    }
+}
+
+/// Keeps a child process running until stopped or dropped.
+pub struct Supervisor {
+    name: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+    /// Starts the child and restarts it with backoff whenever it exits or
+    /// fails `healthy` checks, run every `interval`. Starts and crashes go to `on_event`.
+    pub fn start<F, E>(spec: ChildSpec, interval: Duration, healthy: F, on_event: E) -> Self
+    where
+        F: Fn() -> bool + Send + 'static,
+        E: Fn(ProcessEvent) + Send + 'static,
+    {
+        Self::start_with_clock(spec, interval, healthy, on_event, SystemClock)
+    }
+
+    fn start_with_clock<F, E, C>(
+        spec: ChildSpec,
+        interval: Duration,
+        healthy: F,
+        on_event: E,
+        clock: C,
+    ) -> Self
+    where
+        F: Fn() -> bool + Send + 'static,
+        E: Fn(ProcessEvent) + Send + 'static,
+        C: Clock,
+    {
+        let name = spec.name.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            supervise(&spec, interval, healthy, on_event, &clock, &thread_stop)
+        });
+        Supervisor {
+            name,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops the child and waits for it to exit.
+    pub fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            info!("Stopping {}", self.name);
+            self.stop.store(true, Ordering::SeqCst);
+            if thread.join().is_err() {
+                error!("Supervisor of {} panicked", self.name);
+            }
+        }
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Stops supervised processes, the last one started first.
+pub fn stop_all(supervisors: &Mutex<Vec<Supervisor>>) {
+    // Still stop them if a panic poisoned the lock.
+    let mut supervisors = supervisors.lock().unwrap_or_else(|e| e.into_inner());
+    while let Some(mut supervisor) = supervisors.pop() {
+        supervisor.stop();
+    }
+}
+
+/// Stops the supervised processes in a shared list when dropped, also while a panic
+/// unwinds, so they aren't left running without the bot.
+pub struct StopGuard(pub Arc<Mutex<Vec<Supervisor>>>);
+
+impl Drop for StopGuard {
+    fn drop(&mut self) {
+        stop_all(&self.0);
+    }
+}
+
+/// The supervision loop, run on its own thread.
+fn supervise<F, E, C>(
+    spec: &ChildSpec,
+    interval: Duration,
+    healthy: F,
+    on_event: E,
+    clock: &C,
+    stop: &AtomicBool,
+) where
+    F: Fn() -> bool,
+    E: Fn(ProcessEvent),
+    C: Clock,
+{
+    let mut backoff = Backoff::default();
+    while !stop.load(Ordering::SeqCst) {
+        match spawn(spec) {
+            Ok(mut child) => {
+                info!("Started {} (pid {})", spec.name, child.id());
+                on_event(ProcessEvent::Started);
+                let crash = watch(
+                    spec,
+                    &mut child,
+                    interval,
+                    &healthy,
+                    &mut backoff,
+                    clock,
+                    stop,
+                );
+                if stop.load(Ordering::SeqCst) {
+                    terminate(spec, &mut child);
+                    return;
+                }
+                if let Some(crash) = crash {
+                    on_event(crash);
+                }
+            }
+            Err(e) => error!("Can't start {}: {}", spec.name, e),
+        }
+        let delay = backoff.next_delay();
+        info!("Restarting {} in {} seconds", spec.name, delay.as_secs());
+        if clock.sleep(delay, stop) {
+            return;
+        }
+    }
+}
+
+/// Watches a running child until it exits, stops answering health checks,
+/// or supervision stops. Returns how it crashed, if it did.
+fn watch<F: Fn() -> bool, C: Clock>(
+    spec: &ChildSpec,
+    child: &mut Child,
+    interval: Duration,
+    healthy: &F,
+    backoff: &mut Backoff,
+    clock: &C,
+    stop: &AtomicBool,
+) -> Option<ProcessEvent> {
+    let started = clock.now();
+    let mut failures = 0;
+    loop {
+        if clock.sleep(interval, stop) {
+            return None;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                warn!("{} exited: {}", spec.name, status);
+                return Some(ProcessEvent::Exited(status.to_string()));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Can't check on {}: {}", spec.name, e);
+                terminate(spec, child);
+                return Some(ProcessEvent::Exited(e.to_string()));
+            }
+        }
+        if healthy() {
+            failures = 0;
+            if clock.now() - started >= STABLE_AFTER {
+                backoff.reset();
+            }
+        } else {
+            failures += 1;
+            warn!(
+                "{} failed a health check ({} in a row)",
+                spec.name, failures
+            );
+            if failures >= MAX_FAILED_CHECKS {
+                warn!("{} is not responding, restarting it", spec.name);
+                terminate(spec, child);
+                return Some(ProcessEvent::Unresponsive);
+            }
+        }
+    }
+}
+
+/// Starts the child with its output going to the log.
+fn spawn(spec: &ChildSpec) -> Result<Child, std::io::Error> {
+    let mut child = Command::new(&spec.program)
+        .args(&spec.args)
+        .current_dir(&spec.current_dir)
+        .stdin(if spec.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Answer the prompt, then close stdin.
+    if let (Some(input), Some(mut stdin)) = (&spec.stdin, child.stdin.take()) {
+        writeln!(stdin, "{}", input)?;
+    }
+    if let Some(stdout) = child.stdout.take() {
+        log_lines(&spec.name, stdout, Level::Info);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        log_lines(&spec.name, stderr, Level::Warn);
+    }
+    Ok(child)
+}
+
+/// Logs each line of a child's output, with the child's name as the log target.
+fn log_lines<R: Read + Send + 'static>(target: &str, output: R, level: Level) {
+    let target = target.to_string();
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            match line {
+                Ok(line) => log!(target: &target, level, "{}", line),
+                Err(_) => break,
+            }
+        }
+    });
+}
+
+/// Asks the child to exit with SIGTERM, and kills it if it doesn't in time.
+fn terminate(spec: &ChildSpec, child: &mut Child) {
+    let asked = Command::new("kill")
+        .args(&["-TERM", &child.id().to_string()])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if asked {
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(Some(status)) = child.try_wait() {
+                info!("{} stopped: {}", spec.name, status);
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        warn!("{} didn't stop in time, killing it", spec.name);
+    }
+    if let Err(e) = child.kill() {
+        warn!("Can't kill {}: {}", spec.name, e);
+    }
+    let _ = child.wait();
+}
+
+/// Whether something accepts connections at the address of a URL. A basic health check.
+pub fn accepts_connections(url: &str) -> bool {
+    let addrs = match Url::parse(url).map(|url| url.socket_addrs(|| None)) {
+        Ok(Ok(addrs)) => addrs,
+        _ => return false,
+    };
+    addrs
+        .iter()
+        .any(|addr| TcpStream::connect_timeout(addr, CONNECT_TIMEOUT).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_to_limit() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    /// A clock that moves on without waiting, and records each wait.
+    struct FastClock {
+        now: Mutex<Instant>,
+        waits: Arc<Mutex<Vec<Duration>>>,
+    }
+
+    impl Clock for FastClock {
+        fn now(&self) -> Instant {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration, stop: &AtomicBool) -> bool {
+            *self.now.lock().unwrap() += duration;
+            self.waits.lock().unwrap().push(duration);
+            // Gives the child a moment to run.
+            thread::sleep(Duration::from_millis(5));
+            stop.load(Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn restarts_and_stops_child() {
+        let dir = tempfile::tempdir().unwrap();
+        let count = dir.path().join("starts");
+        let spec = ChildSpec {
+            name: "test_child".to_string(),
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "read password; echo $password >> starts".to_string(),
+            ],
+            current_dir: dir.path().to_str().unwrap().to_string(),
+            stdin: Some("secret".to_string()),
+        };
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let seen = events.clone();
+        let waits = Arc::new(Mutex::new(vec![]));
+        let clock = FastClock {
+            now: Mutex::new(Instant::now()),
+            waits: waits.clone(),
+        };
+        let mut supervisor = Supervisor::start_with_clock(
+            spec,
+            Duration::from_millis(100),
+            || true,
+            move |event| seen.lock().unwrap().push(event),
+            clock,
+        );
+        let starts = || std::fs::read_to_string(&count).unwrap_or_default();
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while starts().lines().count() < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        supervisor.stop();
+
+        assert!(starts().lines().count() >= 3);
+        assert!(starts().lines().all(|l| l == "secret"));
+        let events = events.lock().unwrap();
+        assert_eq!(events[0], ProcessEvent::Started);
+        match events[1] {
+            ProcessEvent::Exited(_) => {}
+            _ => panic!("expected an exit, got {:?}", events[1]),
+        }
+        // Restarts wait twice as long each time.
+        let restarts: Vec<u64> = waits
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|wait| wait.as_secs() > 0)
+            .map(|wait| wait.as_secs())
+            .collect();
+        assert_eq!(restarts[..2], [1, 2]);
+    }
+
+    #[test]
+    fn guard_stops_supervisors() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = ChildSpec {
+            name: "test_child".to_string(),
+            program: "sleep".to_string(),
+            args: vec!["60".to_string()],
+            current_dir: dir.path().to_str().unwrap().to_string(),
+            stdin: None,
+        };
+        let supervisor = Supervisor::start(spec, Duration::from_millis(100), || true, |_| {});
+        let supervisors = Arc::new(Mutex::new(vec![supervisor]));
+        drop(StopGuard(supervisors.clone()));
+        assert!(supervisors.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn health_check() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(accepts_connections(&format!(
+            "http://127.0.0.1:{}/v2/owner",
+            port
+        )));
+        drop(listener);
+        assert!(!accepts_connections(&format!(
+            "http://127.0.0.1:{}/v2/owner",
+            port
+        )));
+        assert!(!accepts_connections("not a url"));
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Owner API supervision settings from config.yml.
+#[derive(Debug, Clone)]
+pub struct OwnerApiOptions {
+    /// grin-wallet executable.
+    pub program: String,
+    /// Seconds between health checks.
+    pub health_interval: u64,
+}
+
+impl Default for OwnerApiOptions {
+    fn default() -> Self {
+        OwnerApiOptions {
+            program: "grin-wallet".to_string(),
+            health_interval: 10,
+        }
+    }
+}
+
+/// A child process to keep running.
+#[derive(Clone)]
+pub struct ChildSpec {
+    /// Name in messages, and the log target its output goes to.
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub current_dir: String,
+    /// Written to stdin once the child starts.
+    pub stdin: Option<String>,
+}
+
+/// Never print the stdin, it may hold the password.
+impl std::fmt::Debug for ChildSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "ChildSpec {{ name: {:?}, program: {:?}, args: {:?}, current_dir: {:?} }}",
+            self.name, self.program, self.args, self.current_dir
+        )
+    }
+}
+
+/// Delay before restarting a child: doubles after every failure, up to a limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    next: Duration,
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            next: initial,
+            initial,
+            max,
+        }
+    }
+
+    /// The delay to wait now. The one after is twice as long.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = std::cmp::min(self.next * 2, self.max);
+        delay
+    }
+
+    /// Starts over after the child has run well for a while.
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::new(Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
+
+/// Something that happened to a supervised process.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessEvent {
+    Started,
+    /// Exited on its own, with the exit status.
+    Exited(String),
+    /// Stopped answering health checks and was killed.
+    Unresponsive,
+}
+
+/// Local grin node supervision settings from config.yml.
+#[derive(Debug, Clone)]
+pub struct NodeOptions {
+    /// grin executable.
+    pub program: String,
+    /// Directory with grin-server.toml, where the node runs.
+    pub dir: String,
+    /// Node API address, e.g. http://127.0.0.1:3413.
+    pub api: String,
+    /// Seconds between health checks.
+    pub health_interval: u64,
+}
+
+impl Default for NodeOptions {
+    fn default() -> Self {
+        NodeOptions {
+            program: "grin".to_string(),
+            dir: String::default(),
+            api: "http://127.0.0.1:3413".to_string(),
+            health_interval: 10,
+        }
+    }
+}
+
+/// Messages from supervisors waiting to be pushed to the chat. Shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct Events(Arc<Mutex<Vec<String>>>);
+
+impl Events {
+    pub fn push(&self, message: String) {
+        self.0.lock().unwrap().push(message);
+    }
+
+    /// Takes the waiting messages, oldest first.
+    pub fn drain(&self) -> Vec<String> {
+        self.0.lock().unwrap().drain(..).collect()
+    }
+}
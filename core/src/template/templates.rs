@@ -1,6 +1,11 @@
+use crate::contacts::types::Contact;
+use crate::diagnostics::types::Check;
+use crate::monitor::types::{Alert, AlertDirection, ScheduledPayment};
 use crate::service::types::WalletInfoGrin;
 use askama::Template;
 
+use std::collections::BTreeMap;
+
 /// Message with post-send information.
 #[derive(Template)]
 #[template(path = "send-success.html")]
@@ -25,7 +30,111 @@ pub struct SeedTemplate<'a> {
     pub seed: &'a str,
 }
 
+/// Message after wallet creation when the seed was written to a file instead.
+#[derive(Template)]
+#[template(path = "seed-file.html")]
+pub struct SeedFileTemplate<'a> {
+    pub path: &'a str,
+}
+
 /// Help text.
 #[derive(Template)]
 #[template(path = "help.html")]
 pub struct HelpTemplate {}
+
+/// Message announcing a received payment.
+#[derive(Template)]
+#[template(path = "payment-received.html")]
+pub struct PaymentReceivedTemplate<'a> {
+    pub title: &'a str,
+    pub amount: f64,
+    pub memo: &'a str,
+    pub status: &'a str,
+    pub id: u32,
+}
+
+/// Message flagging a sent transaction that hasn't confirmed.
+#[derive(Template)]
+#[template(path = "stuck-tx.html")]
+pub struct StuckTxTemplate {
+    pub id: u32,
+    pub age: u64,
+    pub locked: f64,
+}
+
+/// Message when the spendable balance crosses an alert threshold.
+#[derive(Template)]
+#[template(path = "balance-alert.html")]
+pub struct BalanceAlertTemplate {
+    pub direction: AlertDirection,
+    pub threshold: f64,
+    pub spendable: f64,
+}
+
+/// List of balance alerts.
+#[derive(Template)]
+#[template(path = "alerts.html")]
+pub struct AlertsTemplate<'a> {
+    pub alerts: &'a [Alert],
+}
+
+/// Balance and activity summary for a period.
+#[derive(Template)]
+#[template(path = "digest.html")]
+pub struct DigestTemplate<'a> {
+    pub title: &'a str,
+    pub start: &'a str,
+    pub end: &'a str,
+    pub opening: f64,
+    pub closing: f64,
+    pub received: f64,
+    pub received_count: u32,
+    pub sent: f64,
+    pub sent_count: u32,
+    pub fees: f64,
+    pub pending: f64,
+    pub locked: f64,
+}
+
+/// List of scheduled payments.
+#[derive(Template)]
+#[template(path = "schedules.html")]
+pub struct SchedulesTemplate<'a> {
+    pub payments: &'a [ScheduledPayment],
+    pub next_runs: &'a [String],
+}
+
+/// Message asking to approve runs of a scheduled payment missed while the bot was down.
+#[derive(Template)]
+#[template(path = "missed-payment.html")]
+pub struct MissedPaymentTemplate<'a> {
+    pub id: usize,
+    pub amount: f64,
+    pub destination: &'a str,
+    pub missed_runs: u32,
+}
+
+/// Address book.
+#[derive(Template)]
+#[template(path = "contacts.html")]
+pub struct ContactsTemplate<'a> {
+    pub contacts: &'a BTreeMap<String, Contact>,
+}
+
+/// Send details shown before /confirm.
+#[derive(Template)]
+#[template(path = "confirm-send.html")]
+pub struct ConfirmSendTemplate<'a> {
+    pub amount: f64,
+    pub recipient: &'a str,
+    pub fee: f64,
+    pub balance_after: f64,
+    pub timeout: u64,
+}
+
+/// Diagnostics report for /status.
+#[derive(Template)]
+#[template(path = "status.html")]
+pub struct StatusTemplate<'a> {
+    pub checks: &'a [Check],
+}
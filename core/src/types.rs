@@ -1,5 +1,13 @@
+use chrono::{DateTime, Utc};
 use reqwest::Client;
 
+use std::fmt;
+
+use crate::monitor::types::DigestSchedule;
+use crate::security::types::{SeedDelivery, SpendingLimits, UnverifiedBackup};
+use crate::service::types::MnemonicLength;
+use crate::supervisor::types::{Events, NodeOptions};
+
 /// Global application context.
 #[derive(Debug, Clone)]
 pub struct Context {
@@ -7,6 +15,42 @@ pub struct Context {
     pub wallet_dir: String,
     pub owner_endpoint: String,
     pub wallet_password: String,
+    /// Directory for files the bot keeps between restarts.
+    pub data_dir: String,
+    /// Seconds between periodic wallet checks.
+    pub watch_interval: u64,
+    /// Push a message when the wallet receives a payment.
+    pub notify_payments: bool,
+    /// Blocks a sent transaction may stay unconfirmed before it's flagged. 0 disables the check.
+    pub stuck_tx_blocks: u64,
+    /// Digest schedule from config.yml. May be replaced from chat with /digest.
+    pub digest: Option<DigestSchedule>,
+    /// Spending limits from config.yml. Not changeable from chat.
+    pub limits: SpendingLimits,
+    /// Seconds a send waits for /confirm before it's discarded.
+    pub confirm_timeout: u64,
+    /// Base32 authenticator secret from `grinbot totp-setup`. Value-moving commands need a code when set.
+    pub totp_secret: Option<String>,
+    /// Argon2 hash of the PIN from `grinbot pin-setup`. The bot starts locked when set.
+    pub pin_hash: Option<String>,
+    /// Idle minutes before an unlocked session locks again.
+    pub lock_after: u64,
+    /// Refuse every command that changes the wallet, whatever the user's role.
+    pub read_only: bool,
+    /// How the recovery phrase from /create is delivered.
+    pub seed_delivery: SeedDelivery,
+    /// What /send does until the backup of a wallet from /create is verified.
+    pub unverified_backup: UnverifiedBackup,
+    /// Recovery phrase words for /create when the command doesn't say.
+    pub mnemonic_length: MnemonicLength,
+    /// The supervised grin node, if Grin Bot runs one.
+    pub node: Option<NodeOptions>,
+    /// Supervisor messages for the next check to push to the chat.
+    pub events: Events,
+    /// When the bot started, for /status and to tell scheduled runs missed while it was down.
+    pub started: DateTime<Utc>,
+    /// The chat service commands come from.
+    pub transport: Transport,
 }
 
 impl Default for Context {
@@ -16,6 +60,43 @@ impl Default for Context {
             wallet_dir: String::default(),
             owner_endpoint: String::default(),
             wallet_password: String::default(),
+            data_dir: "grinbot_data".to_string(),
+            watch_interval: 60,
+            notify_payments: true,
+            stuck_tx_blocks: 60,
+            digest: None,
+            limits: SpendingLimits::default(),
+            confirm_timeout: 120,
+            totp_secret: None,
+            pin_hash: None,
+            lock_after: 10,
+            read_only: false,
+            seed_delivery: SeedDelivery::default(),
+            unverified_backup: UnverifiedBackup::default(),
+            mnemonic_length: MnemonicLength::default(),
+            node: None,
+            events: Events::default(),
+            started: Utc::now(),
+            transport: Transport::Local,
+        }
+    }
+}
+
+/// Where commands come from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transport {
+    /// grinbot -c on the host.
+    Local,
+    Keybase,
+    Telegram,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transport::Local => write!(f, "local command line"),
+            Transport::Keybase => write!(f, "Keybase"),
+            Transport::Telegram => write!(f, "Telegram"),
         }
     }
 }
@@ -0,0 +1,80 @@
+use keybase_bot_api::chat::ChannelParams;
+use keybase_bot_api::Bot;
+use serde_json::{json, Value};
+
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Smallest lifetime Keybase accepts for exploding messages, in seconds.
+const MIN_EXPLODING_LIFETIME: u64 = 30;
+
+/// Calls a Keybase JSON API (chat, team...) through the bot's Keybase instance and returns the raw reply.
+/// For requests keybase-bot-api doesn't have.
+pub fn call(bot: &Bot, api: &str, request: &Value) -> Result<String, Box<dyn Error>> {
+    let mut child = Command::new(&bot.keybase_path)
+        .arg("--home")
+        .arg(&bot.home_dir)
+        .args(&[api, "api"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    if let Some(stdin) = child.stdin.as_mut() {
+        stdin.write_all(request.to_string().as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Lifetime Keybase will use for an exploding message, in seconds.
+pub fn exploding_lifetime(lifetime: u64) -> u64 {
+    lifetime.max(MIN_EXPLODING_LIFETIME)
+}
+
+/// Sends an exploding message, which Keybase deletes after the lifetime in seconds.
+pub fn send_exploding(
+    bot: &Bot,
+    channel: &ChannelParams,
+    message: &str,
+    lifetime: u64,
+) -> Result<(), Box<dyn Error>> {
+    let request = exploding_request(channel, message, lifetime);
+    let reply: Value = serde_json::from_str(&call(bot, "chat", &request)?)?;
+    match reply.get("error") {
+        Some(error) => Err(error["message"].to_string().into()),
+        None => Ok(()),
+    }
+}
+
+/// Chat API send request for an exploding message.
+fn exploding_request(channel: &ChannelParams, message: &str, lifetime: u64) -> Value {
+    json!({
+        "method": "send",
+        "params": {"options": {
+            "channel": {
+                "name": channel.name,
+                "members_type": channel.members_type,
+                "topic_name": channel.topic_name,
+            },
+            "message": {"body": message},
+            "exploding_lifetime": format!("{}s", exploding_lifetime(lifetime)),
+        }}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exploding_lifetime() {
+        let channel = ChannelParams {
+            name: "bot,user123".to_string(),
+            ..Default::default()
+        };
+        let request = exploding_request(&channel, "secret", 10);
+        assert_eq!(request["params"]["options"]["exploding_lifetime"], "30s");
+        let request = exploding_request(&channel, "secret", 300);
+        assert_eq!(request["params"]["options"]["exploding_lifetime"], "300s");
+    }
+}
@@ -1,23 +1,37 @@
 use grinbot_core::controller::dispatch::{
     get_action, get_command, screen_reducer, tokenize_command,
 };
-use grinbot_core::controller::types::{LoggableState, Screen, State};
-use grinbot_core::types::Context;
+use grinbot_core::controller::types::{Action, LoggableState, Screen, State};
+use grinbot_core::security::roles::Users;
+use grinbot_core::security::types::{Role, Session};
+use grinbot_core::types::{Context, Transport};
 
-use crate::keybase::types::KeybaseMessageParseError;
+use crate::keybase::types::{KeybaseMessageParseError, KeybaseOptions};
+use crate::keybase::{cli, team};
 use redux_rs::{Store, Subscription};
 use regex::Regex;
 
+use futures::channel::mpsc;
 use futures::executor::block_on;
 use futures::prelude::*;
-use futures::stream::StreamExt;
+use futures::stream::{self, StreamExt};
 use keybase_bot_api::chat::{ChannelParams, Notification};
 
 use keybase_bot_api::{ApiError, Bot, Chat};
 use keybase_protocol::chat1::api;
 
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::io;
 use std::process;
+use std::thread;
+use std::time::Duration;
+
+/// Events handled by the main loop.
+enum Event {
+    Notification(Box<Result<Notification, ApiError>>),
+    Tick,
+}
 
 pub struct KeybaseService {}
 
@@ -26,13 +40,26 @@ impl KeybaseService {
         KeybaseService {}
     }
 
+    /// Returns the conversation id, sender, command and originating channel of a chat notification.
     pub fn parse_update(
         notification: Result<Notification, ApiError>,
-    ) -> Result<(i64, Option<String>, Option<String>), Box<dyn Error>> {
-        match notification.unwrap() {
+    ) -> Result<(i64, Option<String>, Option<String>, ChannelParams), Box<dyn Error>> {
+        match notification? {
             Notification::Chat(api::MsgNotification { msg, .. }) => {
                 let summary = msg.ok_or(KeybaseMessageParseError)?;
-                let id = summary.id.ok_or(KeybaseMessageParseError)?;
+                // Pending sends and prompts are kept per conversation, not per message.
+                let id = summary
+                    .convID
+                    .as_ref()
+                    .map(String::as_str)
+                    .and_then(Self::conversation_id)
+                    .ok_or(KeybaseMessageParseError)?;
+                let channel = summary.channel.ok_or(KeybaseMessageParseError)?;
+                let channel = ChannelParams {
+                    name: channel.name.ok_or(KeybaseMessageParseError)?,
+                    members_type: channel.membersType,
+                    topic_name: channel.topicName,
+                };
                 let from_user = summary.sender.ok_or(KeybaseMessageParseError)?.username;
                 let message = summary
                     .content
@@ -40,12 +67,26 @@ impl KeybaseService {
                     .text
                     .ok_or(KeybaseMessageParseError)?
                     .body;
-                Ok((id as i64, from_user, message))
+                Ok((id, from_user, message, channel))
             }
             _ => Err(Box::new(KeybaseMessageParseError)),
         }
     }
 
+    /// Whether a conversation is only between the bot and the sender, or the sender alone.
+    /// Conversation names list their members, separated by commas.
+    fn is_private(channel_name: &str, bot_username: &str, sender: &str) -> bool {
+        channel_name
+            .split(',')
+            .all(|member| member == bot_username || member == sender)
+    }
+
+    /// Numeric id for a hex Keybase conversation id, from its first 60 bits.
+    fn conversation_id(conversation: &str) -> Option<i64> {
+        let prefix = conversation.get(..conversation.len().min(15))?;
+        i64::from_str_radix(prefix, 16).ok()
+    }
+
     /// Returns the next message from the current state.
     pub fn get_keybase_ui(state: &State) -> (i64, String) {
         let id = state.id.unwrap();
@@ -59,17 +100,13 @@ impl KeybaseService {
 
     pub fn start(
         self,
-        from_user: String,
-        wallet_dir: String,
-        owner_endpoint: String,
-        wallet_password: String,
-        log_config: String,
+        users: Users,
+        options: KeybaseOptions,
+        context: Context,
         cli_command: Option<&str>,
         key: String,
         to_user: String, // local bot & paper key user
     ) {
-        // Logging
-        log4rs::init_file(log_config, Default::default()).unwrap();
         info!("Starting Grin Bot...");
         let logging_listener: Subscription<State> = |state: &State| {
             // Log actions with a log level
@@ -78,23 +115,32 @@ impl KeybaseService {
             }
         };
 
-        // Initialize reqwest and app context
-        let http_client = reqwest::Client::new();
-        let context = Context {
-            http_client,
-            wallet_dir,
-            owner_endpoint,
-            wallet_password,
-        };
-
         // Initial state of the bot
         let initial_state = State {
             id: None,
             prev_screen: Screen::Home,
             screen: Screen::Home,
             message: None,
-            context: context,
+            context: Context {
+                transport: if cli_command.is_some() {
+                    Transport::Local
+                } else {
+                    Transport::Keybase
+                },
+                ..context
+            },
             error_level: None,
+            buttons: vec![],
+            sender: None,
+            pending: BTreeMap::new(),
+            awaiting_code: BTreeMap::new(),
+            sessions: if cli_command.is_some() {
+                vec![(None, Session::local())].into_iter().collect()
+            } else {
+                BTreeMap::new()
+            },
+            expires: None,
+            backup_quiz: None,
         };
 
         // The state management store
@@ -110,30 +156,144 @@ impl KeybaseService {
             store.dispatch(action);
             let message = &store.state().message;
             println!("{}", message.clone().unwrap());
+
+            // Sends wait for /confirm or /abort on the terminal.
+            if let Screen::Confirm = store.state().screen {
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer).unwrap();
+                let (command_type, parameters) = tokenize_command(answer.trim());
+                store.dispatch(get_command(command_type, 0, parameters));
+                let message = &store.state().message;
+                println!("{}", message.clone().unwrap());
+            }
             process::exit(0x0100);
         }
 
         // No command line, start bot.
         let mut bot = Bot::new(&to_user, &key).unwrap();
 
-        let notifications = bot.listen().unwrap();
-        let future = notifications.for_each(|notification| {
-            // Unpack Keybase update (command from user).
-            let (id, message_from_user, message) = Self::parse_update(notification).unwrap();
-            // Get the action associated with the command.
-            let action = get_action(id, &message_from_user, message, &from_user);
-            // Dispatch the action.
-            store.dispatch(action);
+        let notifications = bot
+            .listen()
+            .unwrap()
+            .map(|notification| Event::Notification(Box::new(notification)));
+
+        // Wallet checks run between notifications.
+        let (tick_sender, ticks) = mpsc::unbounded();
+        let watch_interval = Duration::from_secs(store.state().context.watch_interval);
+        thread::spawn(move || loop {
+            thread::sleep(watch_interval);
+            if tick_sender.unbounded_send(()).is_err() {
+                break;
+            }
+        });
+
+        // Notifications go to the first admin.
+        let admin = users
+            .iter()
+            .find(|(_, role)| **role == Role::Admin)
+            .map(|(name, _)| name.clone());
+        let private_channel = |user: &str| ChannelParams {
+            name: format!("{},{}", bot.username, user),
+            ..Default::default()
+        };
+
+        let events = stream::select(notifications, ticks.map(|_| Event::Tick));
+        let future = events.for_each(|event| {
+            let channel = match event {
+                Event::Notification(notification) => {
+                    // Unpack Keybase update (command from user).
+                    let (id, message_from_user, message, channel) =
+                        match Self::parse_update(*notification) {
+                            Ok(update) => update,
+                            Err(e) => {
+                                warn!("Skipped Keybase notification: {}", e);
+                                return future::ready(());
+                            }
+                        };
+                    let sender = match &message_from_user {
+                        Some(user) => user.clone(),
+                        None => return future::ready(()),
+                    };
+                    let team = channel.members_type.as_ref().map(String::as_str) == Some("team");
+                    let action = if team {
+                        // In team channels, only commands are for the bot.
+                        if !message.as_ref().map_or(false, |m| m.starts_with('/')) {
+                            return future::ready(());
+                        }
+                        // Teams that aren't configured are ignored, without asking Keybase
+                        // for their members.
+                        if team::configured(
+                            &options.teams,
+                            &channel.name,
+                            channel.topic_name.as_ref().map(String::as_str),
+                        )
+                        .is_none()
+                        {
+                            return future::ready(());
+                        }
+                        // Team roles decide what members may do.
+                        let members = team::members(&bot, &channel.name).unwrap_or_else(|e| {
+                            warn!("{}", e);
+                            Users::new()
+                        });
+                        get_action(id, &message_from_user, message, &members)
+                    } else {
+                        get_action(id, &message_from_user, message, &users)
+                    }
+                    .sent_by(&message_from_user);
+                    let private = !team && Self::is_private(&channel.name, &bot.username, &sender);
+                    // Commands answered with a secret only run in a private conversation.
+                    if action.asks_secret() && !private {
+                        let notice = "Answers to this command are secret. Send it in your private conversation with the bot.";
+                        if let Err(e) = bot.send_msg(&channel, notice) {
+                            error!("Failed to send message: {:?}", e);
+                        }
+                        return future::ready(());
+                    }
+                    // Secrets go to the sender's private conversation, never a team or group.
+                    if action.reveals_secret() && !private {
+                        let notice = "The reply is in your private conversation with the bot.";
+                        if let Err(e) = bot.send_msg(&channel, notice) {
+                            error!("Failed to send message: {:?}", e);
+                        }
+                        store.dispatch(action);
+                        private_channel(&sender)
+                    } else {
+                        store.dispatch(action);
+                        // Reply to the originating conversation.
+                        channel
+                    }
+                }
+                Event::Tick => {
+                    let admin = match &admin {
+                        Some(admin) => admin.clone(),
+                        None => return future::ready(()),
+                    };
+                    store.dispatch(Action::Tick(0));
+                    // Only send a message if a check had something to report.
+                    if store.state().message.is_none() {
+                        return future::ready(());
+                    }
+                    private_channel(&admin)
+                }
+            };
             // Use the updated state to return an updated UI (reply message).
             let (_id, message) = KeybaseService::get_keybase_ui(store.state());
-            // Create channel parameters.
-            let channel = ChannelParams {
-                name: format!("{},{}", bot.username, from_user),
-                ..Default::default()
-            };
+            // Replies with a secret explode instead of staying in the conversation.
+            if let Some(lifetime) = store.state().expires {
+                let message = format!(
+                    "{}\n\n_Sent as an exploding message. Keybase deletes it after {} seconds._",
+                    message,
+                    cli::exploding_lifetime(lifetime)
+                );
+                if let Err(e) = cli::send_exploding(&bot, &channel, &message, lifetime) {
+                    error!("Failed to send exploding message: {}", e);
+                }
+                return future::ready(());
+            }
             // Send reply to user.
             if let Err(e) = bot.send_msg(&channel, &message) {
-                println!("Failed to send message: {:?}", e);
+                error!("Failed to send message: {:?}", e);
             }
             future::ready(())
         });
@@ -162,6 +322,12 @@ mod tests {
     use grinbot_core::controller::types::Action;
     use keybase_bot_api::chat::Notification;
 
+    fn users() -> Users {
+        vec![("user123".to_string(), Role::Admin)]
+            .into_iter()
+            .collect()
+    }
+
     #[test]
     fn raw_callback_query_update() {
         let json = r#"{
@@ -205,10 +371,11 @@ mod tests {
         "#;
 
         let notification = serde_json::from_str::<Notification>(json).unwrap();
-        let (id, from_user, message) = KeybaseService::parse_update(Ok(notification)).unwrap();
+        let (id, from_user, message, _channel) =
+            KeybaseService::parse_update(Ok(notification)).unwrap();
         assert_eq!(
-            Action::Home(99),
-            get_action(id, &from_user, message, &"user123".to_string())
+            Action::Home(0x1234),
+            get_action(id, &from_user, message, &users())
         );
     }
 
@@ -254,10 +421,11 @@ mod tests {
         }
         "#;
         let notification = serde_json::from_str::<Notification>(json).unwrap();
-        let (id, from_user, message) = KeybaseService::parse_update(Ok(notification)).unwrap();
+        let (id, from_user, message, _channel) =
+            KeybaseService::parse_update(Ok(notification)).unwrap();
         assert_eq!(
-            Action::Back(99),
-            get_action(id, &from_user, message, &"user123".to_string())
+            Action::Back(0x1234),
+            get_action(id, &from_user, message, &users())
         );
     }
 
@@ -303,11 +471,19 @@ mod tests {
         }
         "#;
         let notification = serde_json::from_str::<Notification>(json).unwrap();
-        let (id, from_user, message) = KeybaseService::parse_update(Ok(notification)).unwrap();
+        let (id, from_user, message, _channel) =
+            KeybaseService::parse_update(Ok(notification)).unwrap();
         assert_eq!(
-            Action::WrongUsername(101),
-            get_action(id, &from_user, message, &"user123".to_string())
+            Action::WrongUsername(0x1234),
+            get_action(id, &from_user, message, &users())
         );
     }
 
+    #[test]
+    fn private_conversations() {
+        assert!(KeybaseService::is_private("bot,alice", "bot", "alice"));
+        assert!(KeybaseService::is_private("alice", "alice", "alice"));
+        assert!(!KeybaseService::is_private("alice,bob,bot", "bot", "alice"));
+    }
+
 }
@@ -1,6 +1,9 @@
+/// Keybase JSON API calls missing from keybase-bot-api, run with the keybase command
+pub mod cli;
 /// Services associated with Keybase
 pub mod keybase;
 pub use keybase::KeybaseService;
-
+/// Keybase team channels and member roles
+pub mod team;
 /// Types for Keybase service
 pub mod types;
@@ -0,0 +1,107 @@
+use grinbot_core::security::roles::Users;
+use grinbot_core::security::types::Role;
+use keybase_bot_api::Bot;
+use serde_json::{json, Value};
+
+use std::error::Error;
+
+use crate::keybase::cli;
+use crate::keybase::types::{KeybaseTeam, KeybaseTeamError};
+
+/// Bot role for members listed under a Keybase team role.
+/// Readers may look, writers may spend, owners and admins may do everything. Bots get nothing.
+pub fn role(team_role: &str) -> Option<Role> {
+    match team_role {
+        "owners" | "admins" => Some(Role::Admin),
+        "writers" => Some(Role::Spender),
+        "readers" => Some(Role::Viewer),
+        _ => None,
+    }
+}
+
+/// The configured team a channel belongs to, if the bot answers there.
+pub fn configured<'a>(
+    teams: &'a [KeybaseTeam],
+    team_name: &str,
+    topic_name: Option<&str>,
+) -> Option<&'a KeybaseTeam> {
+    teams.iter().find(|team| {
+        team.name == team_name
+            && (team.channels.is_empty()
+                || topic_name.map_or(false, |topic| team.channels.iter().any(|c| c == topic)))
+    })
+}
+
+/// Gets the members of a team and their bot roles, through the bot's Keybase instance.
+pub fn members(bot: &Bot, team_name: &str) -> Result<Users, Box<dyn Error>> {
+    let request = json!({
+        "method": "list-team-memberships",
+        "params": {"options": {"team": team_name}}
+    });
+    parse_members(&cli::call(bot, "team", &request)?)
+}
+
+/// Reads the usernames and bot roles from a list-team-memberships reply.
+pub fn parse_members(reply: &str) -> Result<Users, Box<dyn Error>> {
+    let reply: Value = serde_json::from_str(reply)?;
+    if let Some(error) = reply.get("error") {
+        return Err(Box::new(KeybaseTeamError(
+            error["message"].as_str().unwrap_or_default().to_string(),
+        )));
+    }
+    let members = reply["result"]["members"]
+        .as_object()
+        .ok_or_else(|| KeybaseTeamError("no members in reply".to_string()))?;
+
+    let mut users = Users::new();
+    for (team_role, list) in members {
+        let role = match role(team_role) {
+            Some(role) => role,
+            None => continue,
+        };
+        for member in list.as_array().into_iter().flatten() {
+            if let Some(username) = member["username"].as_str() {
+                users.insert(username.to_string(), role);
+            }
+        }
+    }
+    Ok(users)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn team_roles_map_to_bot_roles() {
+        let reply = r#"{"result": {"teamName": "treasury", "members": {
+            "owners": [{"username": "alice"}],
+            "admins": [],
+            "writers": [{"username": "bob"}],
+            "readers": [{"username": "carol"}],
+            "bots": [{"username": "somebot"}]
+        }}}"#;
+        let users = parse_members(reply).unwrap();
+        assert_eq!(users.get("alice"), Some(&Role::Admin));
+        assert_eq!(users.get("bob"), Some(&Role::Spender));
+        assert_eq!(users.get("carol"), Some(&Role::Viewer));
+        assert_eq!(users.get("somebot"), None);
+
+        let error = r#"{"error": {"code": 2623, "message": "You are not a member of this team"}}"#;
+        assert_eq!(
+            parse_members(error).unwrap_err().to_string(),
+            KeybaseTeamError("You are not a member of this team".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn configured_channels() {
+        let teams = vec![KeybaseTeam {
+            name: "treasury".to_string(),
+            channels: vec!["payments".to_string()],
+        }];
+        assert!(configured(&teams, "treasury", Some("payments")).is_some());
+        assert!(configured(&teams, "treasury", Some("general")).is_none());
+        assert!(configured(&teams, "other", Some("payments")).is_none());
+    }
+}
@@ -11,3 +11,30 @@ impl fmt::Display for KeybaseMessageParseError {
         write!(f, "Error parsing Keybase message.")
     }
 }
+
+/// Opt-in Keybase features from config.yml.
+#[derive(Debug, Clone, Default)]
+pub struct KeybaseOptions {
+    /// Teams the bot answers commands in.
+    pub teams: Vec<KeybaseTeam>,
+}
+
+/// Keybase team whose channels the bot answers in, from config.yml.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeybaseTeam {
+    pub name: String,
+    /// Channels (topic names) the bot answers in. Empty means all of them.
+    pub channels: Vec<String>,
+}
+
+/// The team API didn't return the members of a team.
+#[derive(Debug)]
+pub struct KeybaseTeamError(pub String);
+
+impl Error for KeybaseTeamError {}
+
+impl fmt::Display for KeybaseTeamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error getting Keybase team members: {}", self.0)
+    }
+}
@@ -1,8 +1,5 @@
-# script to start the node, wallet owner API,
-# and the bot in docker
-cd /node
-grin server run &
-cd /mywallet
-grin-wallet -p pass owner_api &
+# script to start the bot in docker.
+# The bot starts the node and the wallet owner API itself
+# (supervise under node and wallet in config.yml).
 cd /grinbot
 cargo run
@@ -0,0 +1,805 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+use grinbot_core::monitor::types::DigestSchedule;
+use grinbot_core::security::roles::Users;
+use grinbot_core::security::types::{Role, SeedDelivery, SpendingLimits, UnverifiedBackup};
+use grinbot_core::service::types::MnemonicLength;
+use grinbot_core::supervisor::types::{NodeOptions, OwnerApiOptions};
+use grinbot_core::types::Context;
+use grinbot_keybase_service::keybase::types::{KeybaseOptions, KeybaseTeam};
+use grinbot_telegram_service::telegram::types::TelegramOptions;
+
+/// config.yml, checked as it's read. Unknown keys are refused so typos don't go unnoticed.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Set in the sample config.yml until it's edited.
+    #[serde(default)]
+    pub sample: bool,
+    #[serde(default)]
+    pub transport: ChatService,
+    pub wallet: WalletConfig,
+    /// The local grin node, if any.
+    pub node: Option<NodeConfig>,
+    /// Seconds between owner API and node health checks.
+    #[serde(default = "default_health_interval")]
+    pub health_interval: u64,
+    /// Required with transport: keybase.
+    pub keybase: Option<KeybaseConfig>,
+    /// Required with transport: telegram.
+    pub telegram: Option<TelegramConfig>,
+    /// Users and their roles. Without it, the transport's single user is admin.
+    pub users: Option<Vec<UserConfig>>,
+    /// Directory for files the bot keeps between restarts.
+    #[serde(default = "default_data_dir", deserialize_with = "path")]
+    pub data_dir: String,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+}
+
+/// The chat service commands come from.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatService {
+    Keybase,
+    Telegram,
+}
+
+impl Default for ChatService {
+    fn default() -> Self {
+        ChatService::Keybase
+    }
+}
+
+/// The wallet, its owner API, and wallet creation.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WalletConfig {
+    /// Wallet directory, either current or future after /create.
+    #[serde(deserialize_with = "path")]
+    pub dir: String,
+    /// Used when creating the wallet and with the owner API.
+    pub password: String,
+    #[serde(default = "default_owner_endpoint", deserialize_with = "url")]
+    pub owner_endpoint: String,
+    /// Run grin-wallet owner_api with the bot.
+    #[serde(default)]
+    pub supervise: bool,
+    #[serde(default = "default_grin_wallet_path", deserialize_with = "path")]
+    pub grin_wallet_path: String,
+    #[serde(default, deserialize_with = "mnemonic_length")]
+    pub mnemonic_words: MnemonicLength,
+    #[serde(default)]
+    pub seed_delivery: SeedMethod,
+    /// Seconds before an ephemeral recovery phrase is removed.
+    #[serde(default = "default_seed_lifetime")]
+    pub seed_lifetime: u64,
+    #[serde(default, deserialize_with = "unverified_backup")]
+    pub unverified_backup: UnverifiedBackup,
+}
+
+/// How the recovery phrase from /create is delivered.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeedMethod {
+    Ephemeral,
+    File,
+}
+
+impl Default for SeedMethod {
+    fn default() -> Self {
+        SeedMethod::Ephemeral
+    }
+}
+
+/// A local grin node.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    /// Run grin server with the bot.
+    #[serde(default)]
+    pub supervise: bool,
+    /// Directory with grin-server.toml.
+    #[serde(deserialize_with = "path")]
+    pub dir: String,
+    #[serde(default = "default_grin_path", deserialize_with = "path")]
+    pub grin_path: String,
+    #[serde(default = "default_node_api", deserialize_with = "url")]
+    pub api: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeybaseConfig {
+    /// The user commands are sent from, usually on mobile. Required without `users`.
+    pub from_user: Option<String>,
+    /// The local user of the paper key. May be the same as from_user.
+    pub to_user: String,
+    pub paper_key: String,
+    #[serde(default)]
+    pub teams: Vec<TeamConfig>,
+}
+
+/// A Keybase team the bot answers in. No channels means all of them.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TeamConfig {
+    pub name: String,
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// Numeric user id. Usernames can be changed and then claimed by someone else.
+    /// Required without `users`.
+    #[serde(default, deserialize_with = "optional_telegram_id")]
+    pub user_id: Option<String>,
+    pub bot_key: String,
+    /// Group chat ids the bot answers in.
+    #[serde(default)]
+    pub groups: Vec<i64>,
+    /// Answer read-only inline queries.
+    #[serde(default)]
+    pub inline: bool,
+}
+
+/// Just the part of config.yml `grinbot whoami` needs, before the user id is known.
+#[derive(Deserialize)]
+struct WhoamiConfig {
+    #[serde(default)]
+    transport: ChatService,
+    telegram: Option<WhoamiTelegramConfig>,
+}
+
+#[derive(Deserialize)]
+struct WhoamiTelegramConfig {
+    bot_key: String,
+}
+
+/// A user, by Keybase name or Telegram id.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    #[serde(alias = "name")]
+    pub id: String,
+    #[serde(deserialize_with = "role")]
+    pub role: Role,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Seconds between wallet checks.
+    pub watch_interval: u64,
+    /// Message when a payment is received.
+    pub notify_payments: bool,
+    /// Blocks a sent transaction may stay unconfirmed before it's flagged.
+    pub stuck_tx_blocks: u64,
+    #[serde(deserialize_with = "digest")]
+    pub digest: Option<DigestSchedule>,
+}
+
+/// Send limits. Only changeable here, never from chat.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Seconds a send waits for /confirm.
+    pub confirm_timeout: u64,
+    pub per_tx: Option<f64>,
+    pub per_day: Option<f64>,
+    pub per_week: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// Authenticator secret from grinbot totp-setup.
+    pub totp_secret: Option<String>,
+    /// PIN hash from grinbot pin-setup.
+    pub pin_hash: Option<String>,
+    /// Idle minutes before the bot locks again.
+    pub lock_after: u64,
+    /// Refuse every command that changes the wallet.
+    pub read_only: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// log4rs config file.
+    #[serde(deserialize_with = "path")]
+    pub config: String,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        let defaults = Context::default();
+        NotificationsConfig {
+            watch_interval: defaults.watch_interval,
+            notify_payments: defaults.notify_payments,
+            stuck_tx_blocks: defaults.stuck_tx_blocks,
+            digest: None,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            confirm_timeout: Context::default().confirm_timeout,
+            per_tx: None,
+            per_day: None,
+            per_week: None,
+        }
+    }
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        let defaults = Context::default();
+        SecurityConfig {
+            totp_secret: None,
+            pin_hash: None,
+            lock_after: defaults.lock_after,
+            read_only: defaults.read_only,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            config: "logging.yml".to_string(),
+        }
+    }
+}
+
+/// config.yml can't be used. Says where, when the YAML parser knows.
+#[derive(Debug, PartialEq)]
+pub struct ConfigError(pub String);
+
+impl Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid config.yml: {}", self.0)
+    }
+}
+
+impl Config {
+    /// Reads and checks a config file.
+    pub fn load(config_path: &str) -> Result<Self, ConfigError> {
+        Config::parse(&read(config_path)?)
+    }
+
+    /// Reads only the Telegram bot key, without checking the rest of the file.
+    /// None when the bot uses Keybase.
+    pub fn load_bot_key(config_path: &str) -> Result<Option<String>, ConfigError> {
+        Config::parse_bot_key(&read(config_path)?)
+    }
+
+    fn parse_bot_key(text: &str) -> Result<Option<String>, ConfigError> {
+        let config: WhoamiConfig =
+            serde_yaml::from_str(text).map_err(|e| ConfigError(e.to_string()))?;
+        match (config.transport, config.telegram) {
+            (ChatService::Keybase, _) => Ok(None),
+            (ChatService::Telegram, Some(telegram)) => Ok(Some(telegram.bot_key)),
+            (ChatService::Telegram, None) => Err(ConfigError(
+                "missing section `telegram`, required with transport: telegram".to_string(),
+            )),
+        }
+    }
+
+    /// Checks config.yml text: types, URLs and paths as it's deserialized,
+    /// then the section of the chat service in use and the users.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let config: Config = serde_yaml::from_str(text).map_err(|e| ConfigError(e.to_string()))?;
+        let at_line = |key: &str| {
+            line_of(text, key)
+                .map(|line| format!(" at line {}", line))
+                .unwrap_or_default()
+        };
+        let missing = match config.transport {
+            ChatService::Keybase if config.keybase.is_none() => Some("keybase"),
+            ChatService::Telegram if config.telegram.is_none() => Some("telegram"),
+            _ => None,
+        };
+        if let Some(section) = missing {
+            return Err(ConfigError(format!(
+                "missing section `{}`, required with transport: {}{}",
+                section,
+                section,
+                at_line("transport")
+            )));
+        }
+        match &config.users {
+            // Telegram users are only known by id.
+            Some(users) if config.transport == ChatService::Telegram => {
+                for (i, user) in users.iter().enumerate() {
+                    if let Err(e) = check_telegram_id(&user.id) {
+                        return Err(ConfigError(format!(
+                            "users[{}].id: {}{}",
+                            i,
+                            e,
+                            at_line("users")
+                        )));
+                    }
+                }
+            }
+            Some(_) => {}
+            None => {
+                let (section, key) = match config.transport {
+                    ChatService::Keybase => ("keybase", "from_user"),
+                    ChatService::Telegram => ("telegram", "user_id"),
+                };
+                if config.users().is_empty() {
+                    return Err(ConfigError(format!(
+                        "missing `{}.{}`, required without `users`{}",
+                        section,
+                        key,
+                        at_line(section)
+                    )));
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// The context shared by both chat services.
+    pub fn context(&self) -> Context {
+        let wallet = &self.wallet;
+        Context {
+            wallet_dir: wallet.dir.clone(),
+            owner_endpoint: wallet.owner_endpoint.clone(),
+            wallet_password: wallet.password.clone(),
+            data_dir: self.data_dir.clone(),
+            watch_interval: self.notifications.watch_interval,
+            notify_payments: self.notifications.notify_payments,
+            stuck_tx_blocks: self.notifications.stuck_tx_blocks,
+            digest: self.notifications.digest,
+            limits: SpendingLimits {
+                per_tx: self.limits.per_tx,
+                per_day: self.limits.per_day,
+                per_week: self.limits.per_week,
+            },
+            confirm_timeout: self.limits.confirm_timeout,
+            totp_secret: self.security.totp_secret.clone(),
+            pin_hash: self.security.pin_hash.clone(),
+            lock_after: self.security.lock_after,
+            read_only: self.security.read_only,
+            seed_delivery: match wallet.seed_delivery {
+                SeedMethod::Ephemeral => SeedDelivery::Ephemeral(wallet.seed_lifetime),
+                SeedMethod::File => SeedDelivery::File,
+            },
+            unverified_backup: wallet.unverified_backup,
+            mnemonic_length: wallet.mnemonic_words,
+            node: self.node_options(),
+            ..Default::default()
+        }
+    }
+
+    /// How to run the owner API, if the bot supervises it.
+    pub fn owner_api_options(&self) -> Option<OwnerApiOptions> {
+        if !self.wallet.supervise {
+            return None;
+        }
+        Some(OwnerApiOptions {
+            program: self.wallet.grin_wallet_path.clone(),
+            health_interval: self.health_interval,
+        })
+    }
+
+    /// Where and how to run the grin node, if the bot supervises one.
+    pub fn node_options(&self) -> Option<NodeOptions> {
+        match &self.node {
+            Some(node) if node.supervise => Some(NodeOptions {
+                program: node.grin_path.clone(),
+                dir: node.dir.clone(),
+                api: node.api.clone(),
+                health_interval: self.health_interval,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Users who may use the bot, and their roles. Without a users list, the
+    /// transport's single configured user is the only user, as admin.
+    pub fn users(&self) -> Users {
+        match &self.users {
+            Some(users) => users.iter().map(|u| (u.id.clone(), u.role)).collect(),
+            None => {
+                let single_user = match self.transport {
+                    ChatService::Keybase => self.keybase.as_ref().and_then(|k| k.from_user.clone()),
+                    ChatService::Telegram => self.telegram.as_ref().and_then(|t| t.user_id.clone()),
+                };
+                single_user
+                    .into_iter()
+                    .map(|user| (user, Role::Admin))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl KeybaseConfig {
+    pub fn options(&self) -> KeybaseOptions {
+        KeybaseOptions {
+            teams: self
+                .teams
+                .iter()
+                .map(|team| KeybaseTeam {
+                    name: team.name.clone(),
+                    channels: team.channels.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TelegramConfig {
+    pub fn options(&self) -> TelegramOptions {
+        TelegramOptions {
+            groups: self.groups.clone(),
+            inline: self.inline,
+        }
+    }
+}
+
+fn read(config_path: &str) -> Result<String, ConfigError> {
+    fs::read_to_string(config_path)
+        .map_err(|e| ConfigError(format!("can't read {}: {}", config_path, e)))
+}
+
+/// The 1-based line a top-level key is on.
+fn line_of(text: &str, key: &str) -> Option<usize> {
+    text.lines()
+        .position(|line| line.starts_with(key) && line[key.len()..].trim_start().starts_with(':'))
+        .map(|i| i + 1)
+}
+
+fn default_health_interval() -> u64 {
+    OwnerApiOptions::default().health_interval
+}
+
+fn default_data_dir() -> String {
+    Context::default().data_dir
+}
+
+fn default_owner_endpoint() -> String {
+    "http://127.0.0.1:3420/v2/owner".to_string()
+}
+
+fn default_grin_wallet_path() -> String {
+    OwnerApiOptions::default().program
+}
+
+fn default_seed_lifetime() -> u64 {
+    match SeedDelivery::default() {
+        SeedDelivery::Ephemeral(lifetime) => lifetime,
+        SeedDelivery::File => 60,
+    }
+}
+
+fn default_grin_path() -> String {
+    NodeOptions::default().program
+}
+
+fn default_node_api() -> String {
+    NodeOptions::default().api
+}
+
+/// Checks a value as it's read, while the YAML parser still knows its key and line.
+struct Checked<F> {
+    check: F,
+    expected: &'static str,
+}
+
+impl<'de, T, F: Fn(&str) -> Result<T, String>> Visitor<'de> for Checked<F> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expected)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        (self.check)(value).map_err(E::custom)
+    }
+}
+
+fn checked<'de, D, T>(
+    deserializer: D,
+    expected: &'static str,
+    check: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(Checked { check, expected })
+}
+
+/// One of a few words, naming them if it's something else.
+fn one_of<T>(value: &str, expected: &str, parsed: Option<T>) -> Result<T, String> {
+    parsed.ok_or_else(|| format!("{} is not {}", value, expected))
+}
+
+/// An http or https URL.
+fn url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    checked(deserializer, "a URL", |value| {
+        match url::Url::parse(value) {
+            Ok(url) if (url.scheme() == "http" || url.scheme() == "https") && url.has_host() => {
+                Ok(value.to_string())
+            }
+            Ok(_) => Err(format!(
+                "{} is not an http(s) URL like http://127.0.0.1:3420/v2/owner",
+                value
+            )),
+            Err(e) => Err(format!("{} is not a URL: {}", value, e)),
+        }
+    })
+}
+
+/// A file or directory path, or an executable. YAML doesn't expand `~`.
+fn path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    checked(deserializer, "a path", |value| {
+        if value.trim().is_empty() {
+            Err("the path is empty".to_string())
+        } else if value.starts_with('~') {
+            Err(format!(
+                "{} starts with ~, which isn't expanded. Write the full path.",
+                value
+            ))
+        } else if value.contains('\0') {
+            Err("the path has a NUL character".to_string())
+        } else {
+            Ok(value.to_string())
+        }
+    })
+}
+
+/// A numeric Telegram user id. Usernames can't be used, they can be claimed by someone else.
+fn optional_telegram_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    checked(deserializer, "a Telegram user id", check_telegram_id).map(Some)
+}
+
+fn check_telegram_id(value: &str) -> Result<String, String> {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "{} is not a numeric Telegram user id, run grinbot whoami to find yours",
+            value
+        ))
+    }
+}
+
+fn role<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Role, D::Error> {
+    let expected = "viewer, spender or admin";
+    checked(deserializer, expected, |value| {
+        one_of(value, expected, Role::parse(value))
+    })
+}
+
+fn unverified_backup<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<UnverifiedBackup, D::Error> {
+    let expected = "remind or block";
+    checked(deserializer, expected, |value| {
+        let action = match value {
+            "remind" => Some(UnverifiedBackup::Remind),
+            "block" => Some(UnverifiedBackup::Block),
+            _ => None,
+        };
+        one_of(value, expected, action)
+    })
+}
+
+/// A digest schedule, or null for none.
+struct OptionalDigest;
+
+impl<'de> Visitor<'de> for OptionalDigest {
+    type Value = Option<DigestSchedule>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a digest schedule or nothing")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let expected = "a schedule like \"daily 09:00\" or \"weekly 09:00\"";
+        checked(deserializer, expected, |value| {
+            let mut parts = value.splitn(2, ' ');
+            let digest = parts
+                .next()
+                .and_then(|period| DigestSchedule::parse(period, parts.next().unwrap_or_default()));
+            one_of(value, expected, digest).map(Some)
+        })
+    }
+}
+
+fn digest<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DigestSchedule>, D::Error> {
+    deserializer.deserialize_option(OptionalDigest)
+}
+
+fn mnemonic_length<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MnemonicLength, D::Error> {
+    checked(deserializer, "a number of words", |value| {
+        let words = value
+            .parse::<u8>()
+            .map_err(|_| format!("{} is not a number of words", value))?;
+        MnemonicLength::new(words).map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TELEGRAM: &str = "transport: telegram
+wallet:
+  dir: /mywallet
+  password: change_me
+telegram:
+  user_id: 123456789
+  bot_key: 1234:KEY
+";
+
+    fn error(text: &str) -> String {
+        Config::parse(text).err().unwrap().0
+    }
+
+    #[test]
+    fn defaults() {
+        let config = Config::parse(TELEGRAM).unwrap();
+        let context = config.context();
+        assert_eq!(context.owner_endpoint, "http://127.0.0.1:3420/v2/owner");
+        assert_eq!(context.data_dir, "grinbot_data");
+        assert_eq!(context.seed_delivery, SeedDelivery::Ephemeral(60));
+        assert_eq!(context.confirm_timeout, 120);
+        assert!(context.node.is_none());
+        assert!(config.owner_api_options().is_none());
+        assert_eq!(config.logging.config, "logging.yml");
+        assert_eq!(config.users().get("123456789"), Some(&Role::Admin));
+    }
+
+    #[test]
+    fn only_the_transport_in_use_is_required() {
+        assert!(Config::parse(TELEGRAM).is_ok());
+        assert_eq!(
+            error(&TELEGRAM.replace("transport: telegram", "transport: keybase")),
+            "missing section `keybase`, required with transport: keybase at line 1"
+        );
+    }
+
+    #[test]
+    fn whoami_needs_only_the_bot_key() {
+        let text = "transport: telegram\ntelegram:\n  bot_key: 1234:KEY\n";
+        assert!(Config::parse(text).is_err());
+        assert_eq!(
+            Config::parse_bot_key(text),
+            Ok(Some("1234:KEY".to_string()))
+        );
+        assert_eq!(Config::parse_bot_key("transport: keybase\n"), Ok(None));
+    }
+
+    #[test]
+    fn errors_name_the_line_and_key() {
+        let typo = TELEGRAM.replace("  password:", "  passwrd:");
+        let e = error(&typo);
+        assert!(e.contains("unknown field `passwrd`"), "{}", e);
+        assert!(e.contains("line 4"), "{}", e);
+
+        let e = error(&format!("{}limits:\n  per_tx: ten\n", TELEGRAM));
+        assert!(e.contains("limits.per_tx"), "{}", e);
+        assert!(e.contains("line 9"), "{}", e);
+    }
+
+    #[test]
+    fn urls_and_paths_are_checked() {
+        let e = error(&format!(
+            "{}node:\n  dir: /node\n  api: 127.0.0.1:3413\n",
+            TELEGRAM
+        ));
+        assert!(
+            e.starts_with("node.api: 127.0.0.1:3413 is not a URL"),
+            "{}",
+            e
+        );
+        assert!(e.ends_with("at line 10 column 8"), "{}", e);
+
+        let e = error(&TELEGRAM.replace("/mywallet", "~/mywallet"));
+        assert!(e.contains("wallet.dir"), "{}", e);
+        assert!(e.contains("isn't expanded"), "{}", e);
+    }
+
+    #[test]
+    fn values_are_parsed() {
+        let config = Config::parse(&format!(
+            "{}users:\n  - id: 42\n    role: viewer\nnotifications:\n  digest: weekly 18:30\n",
+            TELEGRAM
+        ))
+        .unwrap();
+        assert_eq!(config.users().get("42"), Some(&Role::Viewer));
+        assert!(config.notifications.digest.is_some());
+
+        let config = Config::parse(&format!("{}notifications:\n  digest: ~\n", TELEGRAM)).unwrap();
+        assert!(config.notifications.digest.is_none());
+        let e = error(&format!("{}notifications:\n  digest: hourly\n", TELEGRAM));
+        assert!(
+            e.contains("notifications.digest: hourly is not a schedule"),
+            "{}",
+            e
+        );
+
+        let e = error(&TELEGRAM.replace("123456789", "myname"));
+        assert!(
+            e.contains("telegram.user_id: myname is not a numeric Telegram user id"),
+            "{}",
+            e
+        );
+
+        let e = error(&format!(
+            "{}users:\n  - id: 42\n    role: owner\n",
+            TELEGRAM
+        ));
+        assert!(
+            e.contains("users[0].role: owner is not viewer, spender or admin"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn users_replace_the_single_user() {
+        let users = "users:\n  - id: 42\n    role: admin\n";
+        let text = TELEGRAM.replace("  user_id: 123456789\n", "");
+        assert_eq!(
+            error(&text),
+            "missing `telegram.user_id`, required without `users` at line 5"
+        );
+        let config = Config::parse(&format!("{}{}", text, users)).unwrap();
+        assert_eq!(config.users().len(), 1);
+
+        // Telegram users are checked like telegram.user_id.
+        let e = error(&format!("{}{}", TELEGRAM, users.replace("42", "alice")));
+        assert!(
+            e.starts_with("users[0].id: alice is not a numeric Telegram user id"),
+            "{}",
+            e
+        );
+        assert!(e.ends_with("at line 8"), "{}", e);
+    }
+
+    #[test]
+    fn sample_config() {
+        let config = Config::parse(include_str!("../config.yml")).unwrap();
+        assert!(config.sample);
+        assert_eq!(config.transport, ChatService::Keybase);
+        assert_eq!(config.node_options().unwrap().dir, "/node");
+        assert!(config.owner_api_options().is_some());
+        assert_eq!(
+            config.users().get("your_mobile_username"),
+            Some(&Role::Admin)
+        );
+    }
+}
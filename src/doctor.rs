@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use grinbot_core::diagnostics::status;
+use grinbot_core::diagnostics::types::{Check, CheckLevel};
+
+use crate::config::{ChatService, Config};
+
+/// Checks the configuration and the services Grin Bot needs, prints a checklist,
+/// and returns the exit code: 1 if anything failed.
+pub fn run(config_path: &str) -> i32 {
+    let checks = checks(config_path);
+    for check in &checks {
+        println!("[{}] {}: {}", check.level, check.name, check.detail);
+        if let Some(hint) = &check.hint {
+            println!("      {}", hint);
+        }
+    }
+    if checks.iter().any(|c| c.level == CheckLevel::Failed) {
+        println!("\nSome checks failed. Fix them and run grinbot doctor again.");
+        1
+    } else {
+        println!("\nGrin Bot is ready to start.");
+        0
+    }
+}
+
+/// All checks, stopping after config.yml if it can't be used.
+fn checks(config_path: &str) -> Vec<Check> {
+    let config = match load(config_path) {
+        Ok(config) => config,
+        Err(check) => return vec![check],
+    };
+
+    let mut checks = vec![
+        Check::new(
+            "config.yml",
+            CheckLevel::Ok,
+            format!("{} is valid", config_path),
+        ),
+        logging(&config.logging.config),
+    ];
+
+    let context = config.context();
+    checks.push(wallet_dir(&context.wallet_dir));
+    checks.push(
+        status::api_secret(&context.wallet_dir).with_hint(
+            "Start grin-wallet owner_api in wallet.dir once, it writes .api_secret there.",
+        ),
+    );
+    checks.push(owner_api(&config));
+    checks.push(grin_wallet_version(&config.wallet.grin_wallet_path));
+
+    match (config.transport, &config.telegram, &config.keybase) {
+        (ChatService::Telegram, Some(telegram), _) => {
+            checks.push(telegram_token(&telegram.bot_key))
+        }
+        (ChatService::Keybase, _, Some(keybase)) => {
+            checks.push(keybase_paper_key(&keybase.paper_key))
+        }
+        _ => (),
+    }
+    checks
+}
+
+/// Reads config.yml, or explains why it can't be used.
+fn load(config_path: &str) -> Result<Config, Check> {
+    let text = fs::read_to_string(config_path).map_err(|e| {
+        Check::new(
+            "config.yml",
+            CheckLevel::Failed,
+            format!("can't read {}: {}", config_path, e),
+        )
+        .with_hint(
+            "Run grinbot doctor in the directory with config.yml, or create one with grinbot init.",
+        )
+    })?;
+    parse(&text)
+}
+
+/// Checks config.yml text, and that it's no longer the sample.
+fn parse(text: &str) -> Result<Config, Check> {
+    let config = Config::parse(text).map_err(|e| {
+        Check::new("config.yml", CheckLevel::Failed, e.0).with_hint(
+            "Fix the key on the line in the error. See the sample config.yml in the repository.",
+        )
+    })?;
+    if config.sample {
+        return Err(Check::new(
+            "config.yml",
+            CheckLevel::Failed,
+            "is still the sample".to_string(),
+        )
+        .with_hint("Fill in your own settings and remove the line sample: true."));
+    }
+    Ok(config)
+}
+
+/// Whether log4rs accepts the logging config.
+fn logging(log_config: &str) -> Check {
+    match log4rs::load_config_file(log_config, Default::default()) {
+        Ok(_) => Check::new("logging.yml", CheckLevel::Ok, format!("{} is valid", log_config)),
+        Err(e) => Check::new(
+            "logging.yml",
+            CheckLevel::Failed,
+            format!("{}: {}", log_config, e),
+        )
+        .with_hint("Point config under logging in config.yml at a log4rs YAML file, like logging.yml in the repository."),
+    }
+}
+
+/// Whether the owner API answers. A supervised one only runs with the bot, so it isn't called.
+fn owner_api(config: &Config) -> Check {
+    if config.wallet.supervise {
+        return Check::new(
+            "Owner API",
+            CheckLevel::Warning,
+            "started by grinbot".to_string(),
+        )
+        .with_hint("wallet.supervise is set, so the bot starts grin-wallet owner_api itself.");
+    }
+    status::owner_api(&config.context()).0.with_hint(
+        "Start grin-wallet owner_api in wallet.dir (or set supervise: true under wallet), and check wallet.owner_endpoint.",
+    )
+}
+
+/// Whether the wallet directory exists.
+fn wallet_dir(wallet_dir: &str) -> Check {
+    if Path::new(wallet_dir).is_dir() {
+        Check::new("Wallet directory", CheckLevel::Ok, wallet_dir.to_string())
+    } else {
+        Check::new(
+            "Wallet directory",
+            CheckLevel::Failed,
+            format!("{} doesn't exist", wallet_dir),
+        )
+        .with_hint("Set wallet.dir to your grin-wallet directory, or create it and run grin-wallet owner_api there.")
+    }
+}
+
+/// Whether grin-wallet runs, and is new enough for /create.
+fn grin_wallet_version(program: &str) -> Check {
+    match Command::new(program).arg("--version").output() {
+        Ok(output) => {
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            version_check(&version)
+        }
+        Err(e) => Check::new(
+            "grin-wallet",
+            CheckLevel::Failed,
+            format!("can't run {}: {}", program, e),
+        )
+        .with_hint("Install grin-wallet, or set grin_wallet_path under wallet in config.yml."),
+    }
+}
+
+/// Checks grin-wallet --version output, e.g. "grin-wallet 3.1.0".
+fn version_check(version: &str) -> Check {
+    let major = version
+        .split_whitespace()
+        .last()
+        .and_then(|v| v.split('.').next())
+        .and_then(|major| major.parse::<u32>().ok());
+    match major {
+        Some(major) if major >= 3 => Check::new("grin-wallet", CheckLevel::Ok, version.to_string()),
+        Some(_) => Check::new("grin-wallet", CheckLevel::Warning, version.to_string())
+            .with_hint("/create needs grin-wallet 3.0 or later for the owner API v3."),
+        None => Check::new(
+            "grin-wallet",
+            CheckLevel::Warning,
+            format!("unrecognized version {:?}", version),
+        )
+        .with_hint("Check that wallet.grin_wallet_path points at grin-wallet."),
+    }
+}
+
+/// Whether a Telegram bot token looks like 123456789:AA... from BotFather.
+fn telegram_token(token: &str) -> Check {
+    let mut parts = token.splitn(2, ':');
+    let well_formed = match (parts.next(), parts.next()) {
+        (Some(id), Some(secret)) => {
+            !id.is_empty()
+                && id.chars().all(|c| c.is_ascii_digit())
+                && secret.len() == 35
+                && secret
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        }
+        _ => false,
+    };
+    if well_formed {
+        Check::new("Telegram token", CheckLevel::Ok, "well-formed".to_string())
+    } else {
+        Check::new(
+            "Telegram token",
+            CheckLevel::Failed,
+            "telegram.bot_key is missing or malformed".to_string(),
+        )
+        .with_hint("Copy the token BotFather sent you, like 123456789:AAH... with 35 characters after the colon.")
+    }
+}
+
+/// Whether a Keybase paper key is there. Paper keys are 13 words.
+fn keybase_paper_key(key: &str) -> Check {
+    match key.split_whitespace().count() {
+        0 => Check::new(
+            "Keybase paper key",
+            CheckLevel::Failed,
+            "keybase.paper_key is missing".to_string(),
+        )
+        .with_hint(
+            "Generate a paper key for the bot's Keybase user and put it in keybase.paper_key.",
+        ),
+        13 => Check::new("Keybase paper key", CheckLevel::Ok, "present".to_string()),
+        n => Check::new(
+            "Keybase paper key",
+            CheckLevel::Warning,
+            format!("has {} words, paper keys have 13", n),
+        )
+        .with_hint("Check that keybase.paper_key holds the whole paper key."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn telegram_tokens() {
+        let token = "123456789:AAHdqTcvCH1vGWJxfSeofSAs0K5PALDsaw_";
+        assert_eq!(telegram_token(token).level, CheckLevel::Ok);
+        assert_eq!(telegram_token("123456789").level, CheckLevel::Failed);
+        assert_eq!(
+            telegram_token("abc:AAHdqTcvCH1vGWJxfSeofSAs0K5PALDsaw_").level,
+            CheckLevel::Failed
+        );
+        assert!(telegram_token("").hint.is_some());
+    }
+
+    #[test]
+    fn grin_wallet_versions() {
+        assert_eq!(version_check("grin-wallet 3.1.0").level, CheckLevel::Ok);
+        assert_eq!(
+            version_check("grin-wallet 2.0.0").level,
+            CheckLevel::Warning
+        );
+        assert_eq!(version_check("").level, CheckLevel::Warning);
+    }
+
+    #[test]
+    fn config_errors() {
+        let check = parse("wallet:\n  dir: /mywallet\n").err().unwrap();
+        assert_eq!(check.level, CheckLevel::Failed);
+        assert!(check.detail.contains("missing field `password`"));
+
+        let check = parse("sample: true\nwallet:\n  dir: /w\n  password: p\nkeybase:\n  from_user: a\n  to_user: a\n  paper_key: k\n")
+            .err()
+            .unwrap();
+        assert_eq!(check.detail, "is still the sample");
+    }
+
+    #[test]
+    fn supervised_owner_api_is_not_called() {
+        let config = parse("wallet:\n  dir: /w\n  password: p\n  owner_endpoint: http://127.0.0.1:1/v2/owner\n  supervise: true\nkeybase:\n  from_user: a\n  to_user: a\n  paper_key: k\n")
+            .unwrap();
+        let check = owner_api(&config);
+        assert_eq!(check.level, CheckLevel::Warning);
+        assert_eq!(check.detail, "started by grinbot");
+    }
+}
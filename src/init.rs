@@ -0,0 +1,290 @@
+use clap::ArgMatches;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+/// logging.yml written by init: the one shipped with Grin Bot.
+const LOGGING_YML: &str = include_str!("../logging.yml");
+
+/// Flags of settings that are read without echo when asked for.
+const SECRETS: &[&str] = &["wallet-password", "telegram-token", "keybase-paper-key"];
+
+/// Owner endpoint when none is given.
+const DEFAULT_OWNER_ENDPOINT: &str = "http://127.0.0.1:3420/v2/owner";
+
+/// Settings asked for by init.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Answers {
+    pub telegram: bool,
+    pub wallet_dir: String,
+    pub owner_endpoint: String,
+    pub wallet_password: String,
+    pub telegram_bot_key: String,
+    pub telegram_user_id: String,
+    pub keybase_from_user: String,
+    pub keybase_to_user: String,
+    pub keybase_paper_key: String,
+}
+
+/// Writes config.yml and logging.yml, from prompts or, with --non-interactive,
+/// from flags alone. Returns the exit code.
+pub fn run(matches: &ArgMatches) -> i32 {
+    let interactive = !matches.is_present("non-interactive");
+    let dir = matches.value_of("dir").unwrap_or(".");
+    let force = matches.is_present("force");
+
+    // Checked before writing either file, so a refused init leaves nothing half-written.
+    let config_path = Path::new(dir).join("config.yml");
+    let logging_path = Path::new(dir).join("logging.yml");
+    if !force {
+        if let Some(path) = [&config_path, &logging_path].iter().find(|p| p.exists()) {
+            eprintln!(
+                "{} already exists. Use --force to replace it.",
+                path.display()
+            );
+            return 1;
+        }
+    }
+
+    let answers = match answers(matches, interactive) {
+        Ok(answers) => answers,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    for (path, contents) in &[
+        (&config_path, render(&answers)),
+        (&logging_path, LOGGING_YML.to_string()),
+    ] {
+        if let Err(e) = write_private(path, contents, force) {
+            eprintln!("Can't write {}: {}", path.display(), e);
+            if e.kind() == io::ErrorKind::AlreadyExists {
+                eprintln!("Use --force to replace it.");
+            }
+            return 1;
+        }
+        println!("Wrote {}", path.display());
+    }
+    println!("\nOnly your user can read them. Run grinbot doctor to check the setup.");
+    0
+}
+
+/// Collects the settings. Flags answer prompts in advance; without prompts,
+/// every required setting must come from a flag.
+fn answers(matches: &ArgMatches, interactive: bool) -> Result<Answers, String> {
+    let get = |key: &str, prompt: &str, default: Option<&str>| -> Result<String, String> {
+        match (matches.value_of(key), interactive) {
+            (Some(value), _) => Ok(value.to_string()),
+            (None, true) => ask(prompt, default, SECRETS.contains(&key)).map_err(|e| e.to_string()),
+            (None, false) => default
+                .map(|d| d.to_string())
+                .ok_or_else(|| format!("--{} is required with --non-interactive", key)),
+        }
+    };
+
+    let transport = get(
+        "transport",
+        "Chat service (keybase or telegram)",
+        Some("keybase"),
+    )?;
+    let telegram = match transport.to_lowercase().as_str() {
+        "telegram" => true,
+        "keybase" => false,
+        other => {
+            return Err(format!(
+                "Unknown chat service {}, use keybase or telegram",
+                other
+            ))
+        }
+    };
+
+    let mut answers = Answers {
+        telegram,
+        wallet_dir: get("wallet-dir", "Wallet directory", None)?,
+        owner_endpoint: get(
+            "owner-endpoint",
+            "Owner API endpoint",
+            Some(DEFAULT_OWNER_ENDPOINT),
+        )?,
+        ..Default::default()
+    };
+
+    // Keeps the password out of the process list when scripted.
+    let password_env = env::var("GRIN_WALLET_PASSWORD").ok();
+    answers.wallet_password = get(
+        "wallet-password",
+        "Wallet password",
+        password_env.as_ref().map(String::as_str),
+    )?;
+
+    if telegram {
+        answers.telegram_bot_key =
+            get("telegram-token", "Telegram bot token from BotFather", None)?;
+        answers.telegram_user_id = get(
+            "telegram-user-id",
+            "Your numeric Telegram user id (0 for now if unknown, grinbot whoami prints it)",
+            None,
+        )?;
+        if !answers.telegram_user_id.chars().all(|c| c.is_ascii_digit()) {
+            return Err("The Telegram user id must be a number".to_string());
+        }
+    } else {
+        answers.keybase_from_user = get(
+            "keybase-user",
+            "Your Keybase username, the one you'll send commands from",
+            None,
+        )?;
+        answers.keybase_to_user = get(
+            "keybase-bot-user",
+            "Keybase username of the bot's paper key",
+            Some(&answers.keybase_from_user.clone()),
+        )?;
+        answers.keybase_paper_key = get("keybase-paper-key", "Keybase paper key", None)?;
+    }
+    Ok(answers)
+}
+
+/// Asks on the terminal, with a default for an empty answer.
+/// Secrets aren't echoed, and their default isn't shown.
+fn ask(prompt: &str, default: Option<&str>, secret: bool) -> io::Result<String> {
+    loop {
+        let answer = if secret {
+            let prompt = match default {
+                Some(default) if !default.is_empty() => format!("{} [from environment]: ", prompt),
+                _ => format!("{}: ", prompt),
+            };
+            rpassword::prompt_password_stdout(&prompt)?
+        } else {
+            match default {
+                Some(default) if !default.is_empty() => print!("{} [{}]: ", prompt, default),
+                _ => print!("{}: ", prompt),
+            }
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "no answer, stdin closed",
+                ));
+            }
+            answer
+        };
+        match (answer.trim(), default) {
+            ("", Some(default)) => return Ok(default.to_string()),
+            ("", None) => continue,
+            (answer, _) => return Ok(answer.to_string()),
+        }
+    }
+}
+
+/// config.yml with the answers. Optional settings keep their defaults,
+/// see the sample config.yml for them.
+pub fn render(answers: &Answers) -> String {
+    let transport = if answers.telegram {
+        format!(
+            "transport: telegram\n\n\
+             telegram:\n  \
+             user_id: {}\n  \
+             bot_key: {}\n",
+            answers.telegram_user_id,
+            quote(&answers.telegram_bot_key)
+        )
+    } else {
+        format!(
+            "transport: keybase\n\n\
+             keybase:\n  \
+             from_user: {}\n  \
+             to_user: {}\n  \
+             paper_key: {}\n",
+            quote(&answers.keybase_from_user),
+            quote(&answers.keybase_to_user),
+            quote(&answers.keybase_paper_key)
+        )
+    };
+    format!(
+        "# Written by grinbot init. Optional settings are documented in the sample config.yml.\n\n\
+         {}\n\
+         wallet:\n  \
+         dir: {}\n  \
+         password: {}\n  \
+         owner_endpoint: {}\n\n\
+         logging:\n  \
+         config: logging.yml\n",
+        transport,
+        quote(&answers.wallet_dir),
+        quote(&answers.wallet_password),
+        quote(&answers.owner_endpoint)
+    )
+}
+
+/// A double-quoted YAML string, so any answer reads back as written.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes a file only the owner can read: mode 0600, also when replacing one.
+fn write_private(path: &Path, contents: &str, force: bool) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).mode(0o600);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = options.open(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ChatService, Config};
+
+    #[test]
+    fn config_reads_back() {
+        let answers = Answers {
+            telegram: true,
+            wallet_dir: "/home/me/wallet".to_string(),
+            owner_endpoint: DEFAULT_OWNER_ENDPOINT.to_string(),
+            wallet_password: "pa\"ss: #word\\".to_string(),
+            telegram_bot_key: "123456789:AAHdqTcvCH1vGWJxfSeofSAs0K5PALDsaw_".to_string(),
+            telegram_user_id: "99".to_string(),
+            ..Default::default()
+        };
+        let config = Config::parse(&render(&answers)).unwrap();
+        assert_eq!(config.transport, ChatService::Telegram);
+        assert_eq!(config.telegram.unwrap().user_id, Some("99".to_string()));
+        assert_eq!(config.wallet.password, "pa\"ss: #word\\");
+        assert_eq!(config.logging.config, "logging.yml");
+        assert!(config.keybase.is_none());
+
+        let keybase = Answers {
+            telegram: false,
+            keybase_from_user: "alice".to_string(),
+            keybase_to_user: "alicebot".to_string(),
+            keybase_paper_key: "one two three".to_string(),
+            ..answers
+        };
+        let config = Config::parse(&render(&keybase)).unwrap();
+        assert_eq!(config.keybase.unwrap().to_user, "alicebot");
+    }
+
+    #[test]
+    fn files_are_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yml");
+
+        write_private(&path, "a: 1\n", false).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(write_private(&path, "a: 2\n", false).is_err());
+        write_private(&path, "a: 2\n", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a: 2\n");
+    }
+}
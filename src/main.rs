@@ -1,17 +1,98 @@
-use clap::{App, Arg};
-use yaml_rust::{Yaml, YamlLoader};
+mod config;
+mod doctor;
+mod init;
 
-use std::fs::File;
-use std::io::prelude::*;
+use clap::{App, Arg, SubCommand};
+use qrcode::render::unicode;
+use qrcode::QrCode;
 
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use grinbot_core::security::{backup, freeze, session, totp};
+use grinbot_core::supervisor::process::{stop_all, StopGuard};
+use grinbot_core::supervisor::{node, owner_api};
 use grinbot_keybase_service::keybase::KeybaseService;
 use grinbot_telegram_service::telegram::TelegramService;
 
-fn load_config_field(config: &Yaml, field: &str) -> String {
-    config[field]
-        .as_str()
-        .expect(&format!("{} required in config.yml", field))
-        .to_string()
+use crate::config::{ChatService, Config};
+
+/// Prints a new authenticator secret as a QR code and otpauth link.
+fn totp_setup() {
+    let secret = totp::generate_secret();
+    let uri = totp::otpauth_uri(&secret, "grinbot");
+    let qr = QrCode::new(&uri)
+        .unwrap()
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    println!("Scan this code with your authenticator app:\n\n{}\n", qr);
+    println!("Or enter this link: {}\n", uri);
+    println!(
+        "Then add this line under security in config.yml and restart Grin Bot:\n\n  totp_secret: {}",
+        secret
+    );
+}
+
+/// Prints the argon2 hash of a PIN read from the terminal, without echo.
+fn pin_setup() {
+    let read = |prompt: &str| {
+        rpassword::prompt_password_stdout(prompt)
+            .map(|pin| pin.trim().to_string())
+            .unwrap_or_else(|e| {
+                eprintln!("Can't read the PIN: {}", e);
+                process::exit(1);
+            })
+    };
+    let pin = loop {
+        let pin = read("Enter a PIN for /unlock: ");
+        // Also what a closed stdin reads as.
+        if pin.is_empty() {
+            eprintln!("No PIN entered.");
+            process::exit(1);
+        }
+        if read("Enter it again: ") == pin {
+            break pin;
+        }
+        println!("The PINs don't match, try again.");
+    };
+    let pin_hash = session::hash_pin(&pin);
+    println!(
+        "Add this line under security in config.yml and restart Grin Bot:\n\n  pin_hash: \"{}\"",
+        pin_hash
+    );
+}
+
+/// Flags of grinbot init that answer its questions.
+fn init_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    [
+        ("dir", "Directory to write the files to [default: .]"),
+        ("transport", "Chat service: keybase or telegram"),
+        (
+            "wallet-dir",
+            "Wallet directory, with wallet_data and .api_secret",
+        ),
+        ("owner-endpoint", "Owner API endpoint"),
+        (
+            "wallet-password",
+            "Wallet password, or set GRIN_WALLET_PASSWORD to keep it out of the process list",
+        ),
+        ("telegram-token", "Telegram bot token from BotFather"),
+        (
+            "telegram-user-id",
+            "Numeric Telegram user id allowed to use the bot",
+        ),
+        ("keybase-user", "Keybase username allowed to use the bot"),
+        (
+            "keybase-bot-user",
+            "Keybase username of the bot's paper key",
+        ),
+        ("keybase-paper-key", "Keybase paper key of the bot"),
+    ]
+    .iter()
+    .map(|(name, help)| Arg::with_name(name).long(name).takes_value(true).help(help))
+    .collect()
 }
 
 fn main() {
@@ -25,85 +106,169 @@ fn main() {
                 .help("Runs a chat command locally without Telegram")
                 .takes_value(true),
         )
+        .subcommand(
+            SubCommand::with_name("totp-setup")
+                .about("Generates an authenticator secret for sending and cancelling"),
+        )
+        .subcommand(
+            SubCommand::with_name("pin-setup").about("Hashes a PIN for locking the chat session"),
+        )
+        .subcommand(
+            SubCommand::with_name("whoami")
+                .about("Prints the Telegram user id of whoever messages the bot next"),
+        )
+        .subcommand(SubCommand::with_name("unfreeze").about("Re-enables spending after /freeze"))
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Checks config.yml, logging.yml, the wallet and the chat service settings"),
+        )
+        .subcommand(
+            SubCommand::with_name("init")
+                .about("Writes config.yml and logging.yml from a few questions")
+                .arg(
+                    Arg::with_name("non-interactive")
+                        .long("non-interactive")
+                        .help("Takes every setting from flags instead of asking"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Replaces existing config.yml and logging.yml"),
+                )
+                .args(&init_args()),
+        )
+        .subcommand(SubCommand::with_name("backup-verified").about(
+            "Marks the recovery phrase backup verified after a restart lost the /backup check",
+        ))
         .get_matches();
     let cli_command = matches.value_of("command");
 
-    // Load config file
-    let mut f = File::open(&"config.yml").expect("config.yml must exist in current directory.");
-    let mut s = String::new();
-    f.read_to_string(&mut s).unwrap();
-    let yml = YamlLoader::load_from_str(&s).unwrap();
-    let config = &yml[0];
+    if matches.subcommand_matches("totp-setup").is_some() {
+        totp_setup();
+        return;
+    }
+    if matches.subcommand_matches("pin-setup").is_some() {
+        pin_setup();
+        return;
+    }
 
-    // Exit if config has not been edited.
-    let sample_mode: bool = config["sample"].as_bool().unwrap_or(false);
+    if let Some(init_matches) = matches.subcommand_matches("init") {
+        process::exit(init::run(init_matches));
+    }
 
-    if sample_mode {
+    // Check the setup before anything below can panic on it.
+    if matches.subcommand_matches("doctor").is_some() {
+        process::exit(doctor::run("config.yml"));
+    }
+
+    // Print the id to put in config.yml, which may not be complete yet.
+    if matches.subcommand_matches("whoami").is_some() {
+        match Config::load_bot_key("config.yml") {
+            Ok(Some(bot_key)) => TelegramService::whoami(bot_key),
+            Ok(None) => println!(
+                "Keybase users are identified by name. Put from_user under keybase in config.yml."
+            ),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Load config file. Types, URLs and paths are checked here.
+    let config = Config::load("config.yml").unwrap_or_else(|e| {
+        eprintln!("{}\nRun grinbot doctor to check the rest of the setup.", e);
+        process::exit(1);
+    });
+
+    // Exit if config has not been edited.
+    if config.sample {
         panic!("\n\nWarning: Sample mode. Edit config.yml with your own credentials and remove \"sample: true\" to continue.\n\n");
     }
 
-    // Get mode: Keybase (default) or Telegram
-    let telegram_mode: bool = config["telegram_mode"]
-        .as_bool()
-        .expect(&format!("telegram_mode required in config.yml"));
-
-    // Get logging config
-    let log_config = load_config_field(config, "log_config");
-
-    // Get wallet directory, either current or
-    // future after create command.
-    let wallet_dir = load_config_field(config, "wallet_dir");
-
-    // Get owner API endpoint
-    let owner_endpoint = load_config_field(config, "owner_endpoint");
-
-    // Get wallet password. The password used
-    // when creating a wallet and with grin-wallet commands.
-    let wallet_password = load_config_field(config, "wallet_password");
-
-    if telegram_mode {
-        // Get telegram username.
-        // This is the only user who may use the wallet.
-        let telegram_from_user = load_config_field(config, "telegram_from_user");
-
-        // Get bot key
-        let telegram_bot_key = load_config_field(config, "telegram_bot_key");
-
-        // Initialize and start telegram service
-        let ts: TelegramService = TelegramService::new();
-        ts.start(
-            telegram_from_user,
-            wallet_dir.clone(),
-            owner_endpoint.clone(),
-            wallet_password.clone(),
-            log_config.clone(),
-            cli_command,
-            telegram_bot_key,
-        );
-    } else {
-        // Get keybase username.
-        // This is the only user who may use the wallet.
-        let keybase_from_user = load_config_field(config, "keybase_from_user");
-
-        // Get keybase paper key
-        let keybase_paper_key = load_config_field(config, "keybase_paper_key");
-
-        // Get local keybase bot user.
-        // This is the user associated with the paper key.
-        // May be the same as the "from" (usually mobile) user.
-        let keybase_to_user = load_config_field(config, "keybase_to_user");
-
-        // Initialize and start keybase service
-        let ks: KeybaseService = KeybaseService::new();
-        ks.start(
-            keybase_from_user,
-            wallet_dir,
-            owner_endpoint,
-            wallet_password,
-            log_config,
-            cli_command,
-            keybase_paper_key,
-            keybase_to_user,
-        );
+    let context = config.context();
+
+    // Only the host can clear /freeze.
+    if matches.subcommand_matches("unfreeze").is_some() {
+        match freeze::unfreeze(&context.data_dir) {
+            Ok(true) => println!("Unfrozen. Spending is enabled again."),
+            Ok(false) => println!("Grin Bot was not frozen."),
+            Err(e) => {
+                eprintln!("Can't unfreeze: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // For a wallet whose phrase was lost from memory before /backup checked it.
+    if matches.subcommand_matches("backup-verified").is_some() {
+        match backup::mark_verified(&context.data_dir) {
+            Ok(()) => println!("Backup marked verified."),
+            Err(e) => {
+                eprintln!("Can't mark the backup verified: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Logging, for the bot and the output of processes it supervises.
+    log4rs::init_file(&config.logging.config, Default::default()).unwrap();
+
+    // Start the node and owner API with the bot, if configured. Not for local commands.
+    let mut started = vec![];
+    if cli_command.is_none() && matches.subcommand_name().is_none() {
+        if let Some(node_options) = &context.node {
+            started.push(node::start(node_options, &context.events));
+        }
+        if let Some(owner_api_options) = config.owner_api_options() {
+            started.push(owner_api::start(&context, &owner_api_options));
+        }
+    }
+    let supervisors = Arc::new(Mutex::new(started));
+    // Stops them when main returns, as the services do when they can't go on, or panics.
+    let _stop_guard = StopGuard(supervisors.clone());
+    if !supervisors.lock().unwrap().is_empty() {
+        // Stop them cleanly on Ctrl-C or SIGTERM.
+        let on_exit = supervisors.clone();
+        ctrlc::set_handler(move || {
+            stop_all(&on_exit);
+            process::exit(0);
+        })
+        .expect("Can't set the exit handler");
+    }
+
+    // Users who may use the wallet. Telegram users by numeric id: usernames can be
+    // changed and then claimed by someone else.
+    let users = config.users();
+
+    match (config.transport, config.telegram, config.keybase) {
+        (ChatService::Telegram, Some(telegram), _) => {
+            // Initialize and start telegram service
+            let ts: TelegramService = TelegramService::new();
+            ts.start(
+                users,
+                telegram.options(),
+                context,
+                cli_command,
+                telegram.bot_key,
+            );
+        }
+        (ChatService::Keybase, _, Some(keybase)) => {
+            // Initialize and start keybase service
+            let ks: KeybaseService = KeybaseService::new();
+            ks.start(
+                users,
+                keybase.options(),
+                context,
+                cli_command,
+                keybase.paper_key,
+                keybase.to_user,
+            );
+        }
+        // Config::load refuses a config without the section of the transport in use.
+        _ => unreachable!(),
     }
 }
@@ -1,3 +1,6 @@
 /// Services associated with Telegram.
 pub mod telegram;
 pub use telegram::TelegramService;
+
+/// Telegram requests missing from telegram-bot.
+pub mod types;
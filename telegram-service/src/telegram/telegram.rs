@@ -1,14 +1,37 @@
+use crate::telegram::types::{AnswerPersonalInlineQuery, ExpiringMessage, TelegramOptions};
+use futures::future::Future;
 use futures::stream::Stream;
 use grinbot_core::controller::dispatch::{
     get_action, get_command, screen_reducer, tokenize_command,
 };
-use grinbot_core::controller::types::{LoggableState, Screen, State};
-use grinbot_core::types::Context;
+use grinbot_core::controller::types::{Action, LoggableState, Screen, State};
+use grinbot_core::security::roles::Users;
+use grinbot_core::security::types::{Role, Session};
+use grinbot_core::storage;
+use grinbot_core::types::{Context, Transport};
 use redux_rs::{Store, Subscription};
 use telegram_bot::*;
-use tokio_core::reactor::Core;
+use tokio_core::reactor::{Core, Handle, Interval, Timeout};
 
+use std::collections::BTreeMap;
+use std::io;
 use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Data directory file with the chat id of an admin user.
+const OWNER_CHAT_FILE: &str = "telegram_owner_chat.json";
+
+/// Data directory file with sent messages that still have to be deleted.
+const EXPIRING_FILE: &str = "telegram_expiring.json";
+
+/// Seconds after which Telegram no longer lets bots delete a message.
+const DELETE_WINDOW: u64 = 48 * 60 * 60;
+
+/// Events handled by the main loop.
+enum Event {
+    Update(Box<Update>),
+    Tick,
+}
 
 pub struct TelegramService {}
 
@@ -17,73 +40,251 @@ impl TelegramService {
         TelegramService {}
     }
 
-    /// Returns the Action for each Telegram Update (message, callback, inline query)
-    pub fn parse_update(update: Update) -> (i64, Option<String>, Option<String>) {
+    /// Returns the chat id, sender and command for each Telegram Update (message, callback, inline query),
+    /// or None for group messages not addressed to the bot.
+    /// The sender is the numeric user id, which can't be changed or claimed like a username.
+    pub fn parse_update(
+        update: Update,
+        groups: &[i64],
+        bot_username: &str,
+    ) -> Option<(i64, Option<String>, Option<String>)> {
         match update.kind {
             // User sent a message
             UpdateKind::Message(telegram_message) => {
                 let id = telegram_message.chat.id().into();
-                if let MessageKind::Text { data, .. } = telegram_message.kind {
-                    if let MessageChat::Private(from_user) = telegram_message.chat {
-                        return (id, from_user.username, Some(data));
+                let data = match telegram_message.kind {
+                    MessageKind::Text { data, .. } => data,
+                    _ => return Some((id, None, None)),
+                };
+                match telegram_message.chat {
+                    MessageChat::Private(chat_owner) => {
+                        let sender = Self::sender(&chat_owner, &telegram_message.from);
+                        let command = Self::addressed_command(&data, bot_username).unwrap_or(data);
+                        Some((id, sender, Some(command)))
                     }
+                    // In groups, only /command@bot is for the bot.
+                    MessageChat::Group(_) | MessageChat::Supergroup(_) => {
+                        let command = Self::addressed_command(&data, bot_username)?;
+                        let sender = Some(telegram_message.from.id.to_string());
+                        if groups.contains(&id) {
+                            Some((id, sender, Some(command)))
+                        } else {
+                            Some((id, sender, Some("/unsupported".to_string())))
+                        }
+                    }
+                    _ => Some((id, None, None)),
                 }
-                (id, None, None)
             }
             // User clicked a button
             UpdateKind::CallbackQuery(query) => {
                 let id = query.message.chat.id().into();
-                if let MessageChat::Private(from_user) = query.message.chat {
-                    return (id, from_user.username, Some(query.data));
+                match query.message.chat {
+                    MessageChat::Private(chat_owner) => {
+                        let sender = Self::sender(&chat_owner, &query.from);
+                        Some((id, sender, Some(query.data)))
+                    }
+                    MessageChat::Group(_) | MessageChat::Supergroup(_) if groups.contains(&id) => {
+                        Some((id, Some(query.from.id.to_string()), Some(query.data)))
+                    }
+                    _ => Some((id, None, None)),
                 }
-                (id, None, None)
             }
             // User sent an inline (@grinbot123 send...) query
             UpdateKind::InlineQuery(query) => {
                 let id = query.from.id.into();
-                (id, query.from.username, Some("/unsupported".to_string()))
+                Some((
+                    id,
+                    Some(query.from.id.to_string()),
+                    Some("/unsupported".to_string()),
+                ))
             }
 
-            _ => (-1, None, None),
+            _ => Some((-1, None, None)),
+        }
+    }
+
+    /// The command without the bot's name, if it's addressed as /command@bot.
+    fn addressed_command(text: &str, bot_username: &str) -> Option<String> {
+        let (command_type, parameters) = tokenize_command(text);
+        let mut parts = command_type.splitn(2, '@');
+        let command_type = parts.next()?;
+        match parts.next() {
+            Some(name) if name.eq_ignore_ascii_case(bot_username) => {
+                let mut tokens = vec![command_type];
+                tokens.extend(parameters);
+                Some(tokens.join(" "))
+            }
+            _ => None,
+        }
+    }
+
+    /// User id of the sender, if they own the private chat the update came from.
+    fn sender(chat_owner: &User, from: &User) -> Option<String> {
+        if chat_owner.id == from.id {
+            Some(from.id.to_string())
+        } else {
+            warn!(
+                "Message from user {} in the private chat of user {}",
+                from.id, chat_owner.id
+            );
+            None
+        }
+    }
+
+    /// Prints the ids of the first user to message the bot, for config.yml.
+    pub fn whoami(key: String) {
+        let mut core = Core::new().unwrap();
+        let api = Api::configure(key).build(core.handle()).unwrap();
+        println!("Send any message to the bot from the account that will use it...");
+        let future = api
+            .stream()
+            .filter_map(|update| match update.kind {
+                UpdateKind::Message(message) => Some(message),
+                _ => None,
+            })
+            .take(1)
+            .for_each(|message| {
+                let username = message.from.username.unwrap_or_default();
+                println!(
+                    "\nUser id: {} (@{})\nChat id: {}\n\nAdd the user id under telegram in config.yml:\n\n  user_id: {}",
+                    message.from.id,
+                    username,
+                    message.chat.id(),
+                    message.from.id
+                );
+                Ok(())
+            });
+        core.run(future).unwrap();
+    }
+
+    /// Command for a read-only inline query (@grinbot123 balance), if it is one.
+    /// Nothing that moves funds is ever offered inline.
+    pub fn inline_command(query: &str) -> Option<&'static str> {
+        match query.trim() {
+            "balance" => Some("/balance"),
+            "address" => Some("/address"),
+            _ => None,
         }
     }
 
     /// Wraps message in Telegram UI.
+    /// Action for a command, and a notice for the chat it came from.
+    /// Secrets from a group command go to the sender's private chat, and commands
+    /// answered with a secret are refused outside it.
+    fn route(
+        id: i64,
+        from_user: &Option<String>,
+        message: Option<String>,
+        users: &Users,
+        private: bool,
+    ) -> Result<(Action, Option<&'static str>), &'static str> {
+        let action = get_action(id, from_user, message.clone(), users);
+        if private {
+            return Ok((action, None));
+        }
+        if action.asks_secret() {
+            return Err(
+                "Answers to this command are secret. Send it in your private chat with the bot.",
+            );
+        }
+        if action.reveals_secret() {
+            if let Some(user_id) = from_user.as_ref().and_then(|u| u.parse().ok()) {
+                return Ok((
+                    get_action(user_id, from_user, message, users),
+                    Some("The reply is in your private chat with the bot."),
+                ));
+            }
+        }
+        Ok((action, None))
+    }
+
     fn get_telegram_ui(state: &State) -> SendMessage {
         let id = state.id.unwrap();
-        let message = if let Some(m) = &state.message {
+        let mut message = if let Some(m) = &state.message {
             format!("{}", m)
         } else {
             "".to_string()
         };
+        if let Some(lifetime) = state.expires {
+            message.push_str(&format!(
+                "\n\n<i>Grin Bot deletes this message after {} seconds.</i>",
+                lifetime
+            ));
+        }
 
         let mut msg = SendMessage::new(ChatId::new(id), message);
 
-        let keyboard = reply_markup!(
-            reply_keyboard,
-            selective,
-            one_time,
-            resize,
-            ["/balance", "/help"]
-        );
-
         msg.parse_mode(ParseMode::Html);
-        msg.reply_markup(keyboard);
+        if state.buttons.is_empty() {
+            let keyboard = reply_markup!(
+                reply_keyboard,
+                selective,
+                one_time,
+                resize,
+                ["/balance", "/help"]
+            );
+            msg.reply_markup(keyboard);
+        } else {
+            // Commands offered with the message, sent back as callback queries.
+            let rows: Vec<Vec<InlineKeyboardButton>> = state
+                .buttons
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|command| InlineKeyboardButton::callback(command, command))
+                        .collect()
+                })
+                .collect();
+            msg.reply_markup(InlineKeyboardMarkup::from(rows));
+        }
         msg
     }
 
+    /// Deletes a message with a secret at its deadline, and forgets it once deleted.
+    /// A failed delete is tried again on the next start.
+    fn delete_at_deadline(handle: &Handle, api: &Api, data_dir: &str, expiring: ExpiringMessage) {
+        let remaining = expiring.deadline.saturating_sub(unix_now());
+        let timeout = Timeout::new(Duration::from_secs(remaining), handle).unwrap();
+        let api = api.clone();
+        let data_dir = data_dir.to_string();
+        let delete = timeout.map_err(Error::from).and_then(move |_| {
+            api.send(DeleteMessage::new(
+                ChatId::new(expiring.chat),
+                MessageId::new(expiring.message),
+            ))
+            .map(move |_| Self::forget(&data_dir, &expiring))
+        });
+        handle.spawn(delete.map_err(|e| error!("Failed to delete a message with a secret: {}", e)));
+    }
+
+    /// Records a sent message to delete, so it's deleted even if the bot restarts first.
+    fn remember(data_dir: &str, expiring: &ExpiringMessage) -> Result<(), String> {
+        let mut messages: Vec<ExpiringMessage> =
+            storage::load(data_dir, EXPIRING_FILE).map_err(|e| e.to_string())?;
+        messages.push(expiring.clone());
+        storage::save(data_dir, EXPIRING_FILE, &messages).map_err(|e| e.to_string())
+    }
+
+    /// Drops a deleted message from the records.
+    fn forget(data_dir: &str, expiring: &ExpiringMessage) {
+        let result = storage::load(data_dir, EXPIRING_FILE).and_then(|messages: Vec<_>| {
+            let messages: Vec<ExpiringMessage> =
+                messages.into_iter().filter(|m| m != expiring).collect();
+            storage::save(data_dir, EXPIRING_FILE, &messages)
+        });
+        if let Err(e) = result {
+            warn!("Failed to update {}: {}", EXPIRING_FILE, e);
+        }
+    }
+
     pub fn start(
         self,
-        config_user: String,
-        wallet_dir: String,
-        owner_endpoint: String,
-        wallet_password: String,
-        log_config: String,
+        users: Users,
+        options: TelegramOptions,
+        context: Context,
         cli_command: Option<&str>,
         key: String,
     ) {
-        // Logging
-        log4rs::init_file(log_config, Default::default()).unwrap();
         info!("Starting Grin Bot...");
         let logging_listener: Subscription<State> = |state: &State| {
             // Log actions with a log level
@@ -92,23 +293,32 @@ impl TelegramService {
             }
         };
 
-        // Initialize reqwest and app context
-        let http_client = reqwest::Client::new();
-        let context = Context {
-            http_client,
-            wallet_dir,
-            owner_endpoint,
-            wallet_password,
-        };
-
         // Initial state of the bot
         let initial_state = State {
             id: None,
             prev_screen: Screen::Home,
             screen: Screen::Home,
             message: None,
-            context: context,
+            context: Context {
+                transport: if cli_command.is_some() {
+                    Transport::Local
+                } else {
+                    Transport::Telegram
+                },
+                ..context
+            },
             error_level: None,
+            buttons: vec![],
+            sender: None,
+            pending: BTreeMap::new(),
+            awaiting_code: BTreeMap::new(),
+            sessions: if cli_command.is_some() {
+                vec![(None, Session::local())].into_iter().collect()
+            } else {
+                BTreeMap::new()
+            },
+            expires: None,
+            backup_quiz: None,
         };
 
         // The state management store
@@ -124,6 +334,16 @@ impl TelegramService {
             store.dispatch(action);
             let message = &store.state().message;
             println!("{}", message.clone().unwrap());
+
+            // Sends wait for /confirm or /abort on the terminal.
+            if let Screen::Confirm = store.state().screen {
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer).unwrap();
+                let (command_type, parameters) = tokenize_command(answer.trim());
+                store.dispatch(get_command(command_type, 0, parameters));
+                let message = &store.state().message;
+                println!("{}", message.clone().unwrap());
+            }
             process::exit(0x0100);
         }
 
@@ -131,17 +351,158 @@ impl TelegramService {
         let mut core = Core::new().unwrap();
         let api = Api::configure(key).build(core.handle()).unwrap();
 
-        let future = api.stream().for_each(|update| {
-            // Unpack Telegram update (command from user).
-            let (id, from_user, message) = Self::parse_update(update);
-            // Get the action associated with the command.
-            let action = get_action(id, &from_user, message, &config_user);
-            // Dispatch the action.
-            store.dispatch(action);
-            // Use the updated state to return an updated UI (reply message).
-            let ui = TelegramService::get_telegram_ui(store.state());
-            // Send reply to user.
-            api.spawn(ui);
+        // Group commands are addressed to the bot by name.
+        let bot_username = core
+            .run(api.send(GetMe))
+            .unwrap()
+            .username
+            .unwrap_or_default();
+
+        // Chat to send notifications to. Known once an admin has sent a private message.
+        let data_dir = store.state().context.data_dir.clone();
+        let mut owner_chat: Option<i64> = storage::load(&data_dir, OWNER_CHAT_FILE).unwrap_or(None);
+
+        // Messages with a secret sent before a restart: delete the overdue ones now, the rest on time.
+        let handle = core.handle();
+        let expiring: Vec<ExpiringMessage> = storage::load(&data_dir, EXPIRING_FILE)
+            .unwrap_or_else(|e| {
+                error!("Failed to read {}: {}", EXPIRING_FILE, e);
+                vec![]
+            });
+        for message in expiring {
+            if message.deadline + DELETE_WINDOW < unix_now() {
+                error!(
+                    "Message {} in chat {} is too old for the bot to delete, delete it by hand",
+                    message.message, message.chat
+                );
+                Self::forget(&data_dir, &message);
+            } else {
+                Self::delete_at_deadline(&handle, &api, &data_dir, message);
+            }
+        }
+
+        // Wallet checks run between updates.
+        let watch_interval = Duration::from_secs(store.state().context.watch_interval);
+        let ticks = Interval::new(watch_interval, &core.handle())
+            .unwrap()
+            .map(|_| Event::Tick)
+            .map_err(Error::from);
+        let updates = api.stream().map(|update| Event::Update(Box::new(update)));
+
+        let future = updates.select(ticks).for_each(|event| {
+            match event {
+                Event::Update(update) => {
+                    // Answer read-only inline queries with a card, when enabled.
+                    if let UpdateKind::InlineQuery(query) = &update.kind {
+                        if options.inline {
+                            let user_id = query.from.id.to_string();
+                            let from_user = Some(user_id.clone());
+                            let command = Self::inline_command(&query.query)
+                                .filter(|_| users.contains_key(&user_id));
+                            let mut results = vec![];
+                            if let Some(command) = command {
+                                let action = get_action(
+                                    query.from.id.into(),
+                                    &from_user,
+                                    Some(command.to_string()),
+                                    &users,
+                                );
+                                store.dispatch(action.sent_by(&from_user));
+                                let message = store.state().message.clone().unwrap_or_default();
+                                let content = InputTextMessageContent {
+                                    message_text: message,
+                                    parse_mode: Some(ParseMode::Html),
+                                    disable_web_page_preview: true,
+                                };
+                                let title = command.trim_start_matches('/');
+                                results.push(
+                                    InlineQueryResultArticle::new(title, title, content).into(),
+                                );
+                            }
+                            api.spawn(AnswerPersonalInlineQuery::new(query.id.clone(), results));
+                            return Ok(());
+                        }
+                    }
+                    // Unpack Telegram update (command from user).
+                    let (id, from_user, message) =
+                        match Self::parse_update(*update, &options.groups, &bot_username) {
+                            Some(update) => update,
+                            None => return Ok(()),
+                        };
+                    // Private chat ids are the user ids.
+                    let private = from_user.as_ref() == Some(&id.to_string());
+                    // Remember an admin's chat for notifications.
+                    let is_admin =
+                        from_user.as_ref().and_then(|user| users.get(user)) == Some(&Role::Admin);
+                    if private && is_admin && owner_chat != Some(id) {
+                        owner_chat = Some(id);
+                        if let Err(e) = storage::save(&data_dir, OWNER_CHAT_FILE, &owner_chat) {
+                            warn!("Failed to save owner chat: {}", e);
+                        }
+                    }
+                    // Get the action associated with the command.
+                    let action = match Self::route(id, &from_user, message, &users, private) {
+                        Ok((action, notice)) => {
+                            if let Some(notice) = notice {
+                                api.spawn(SendMessage::new(ChatId::new(id), notice));
+                            }
+                            action
+                        }
+                        Err(refusal) => {
+                            api.spawn(SendMessage::new(ChatId::new(id), refusal));
+                            return Ok(());
+                        }
+                    };
+                    // Dispatch the action.
+                    store.dispatch(action.sent_by(&from_user));
+                    // Use the updated state to return an updated UI (reply message).
+                    let ui = TelegramService::get_telegram_ui(store.state());
+                    // Send reply to user. Replies with a secret are deleted after their lifetime.
+                    match store.state().expires {
+                        Some(lifetime) => {
+                            let (delete_handle, delete_api) = (handle.clone(), api.clone());
+                            let data_dir = data_dir.clone();
+                            let send = api.send(ui).map(move |sent| {
+                                let expiring = ExpiringMessage {
+                                    chat: sent.to_source_chat().into(),
+                                    message: sent.to_message_id().into(),
+                                    deadline: unix_now() + lifetime,
+                                };
+                                // Without a record a restart could leave it, so delete it at once.
+                                let expiring = match Self::remember(&data_dir, &expiring) {
+                                    Ok(()) => expiring,
+                                    Err(e) => {
+                                        error!("Failed to record a message with a secret: {}", e);
+                                        ExpiringMessage {
+                                            deadline: 0,
+                                            ..expiring
+                                        }
+                                    }
+                                };
+                                Self::delete_at_deadline(
+                                    &delete_handle,
+                                    &delete_api,
+                                    &data_dir,
+                                    expiring,
+                                );
+                            });
+                            handle.spawn(send.map_err(|e| {
+                                error!("Failed to send a message with a secret: {}", e)
+                            }));
+                        }
+                        None => api.spawn(ui),
+                    }
+                }
+                Event::Tick => {
+                    if let Some(id) = owner_chat {
+                        store.dispatch(Action::Tick(id));
+                        // Only send a message if a check had something to report.
+                        if store.state().message.is_some() {
+                            api.spawn(TelegramService::get_telegram_ui(store.state()));
+                        }
+                    }
+                }
+            }
             Ok(())
         });
 
@@ -151,6 +512,14 @@ impl TelegramService {
     }
 }
 
+/// Seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +527,10 @@ mod tests {
     use grinbot_core::controller::types::Action;
     use telegram_bot::Update;
 
+    fn users() -> Users {
+        vec![("99".to_string(), Role::Admin)].into_iter().collect()
+    }
+
     #[test]
     fn raw_inline_query_update() {
         let json = r#"{
@@ -179,10 +552,11 @@ mod tests {
                 }
             "#;
         let update = serde_json::from_str::<Update>(json).unwrap();
-        let (id, from_user, message) = TelegramService::parse_update(update);
+        let (id, from_user, message) =
+            TelegramService::parse_update(update, &[], "grinbot123").unwrap();
         assert_eq!(
             Action::ModeNotSupported(99),
-            get_action(id, &from_user, message, &"user123".to_string())
+            get_action(id, &from_user, message, &users())
         );
     }
 
@@ -216,10 +590,11 @@ mod tests {
                 }
             }"#;
         let update = serde_json::from_str::<Update>(json).unwrap();
-        let (id, from_user, message) = TelegramService::parse_update(update);
+        let (id, from_user, message) =
+            TelegramService::parse_update(update, &[], "grinbot123").unwrap();
         assert_eq!(
             Action::Home(99),
-            get_action(id, &from_user, message, &"user123".to_string())
+            get_action(id, &from_user, message, &users())
         );
     }
 
@@ -249,22 +624,23 @@ mod tests {
               }
             }"#;
         let update = serde_json::from_str::<Update>(json).unwrap();
-        let (id, from_user, message) = TelegramService::parse_update(update);
+        let (id, from_user, message) =
+            TelegramService::parse_update(update, &[], "grinbot123").unwrap();
         assert_eq!(
             Action::Back(99),
-            get_action(id, &from_user, message, &"user123".to_string())
+            get_action(id, &from_user, message, &users())
         );
     }
 
     #[test]
-    fn raw_wrong_username() {
+    fn raw_sender_not_chat_owner() {
         let json = r#"{
             "update_id":999999,
               "message":{
                 "date": 1568300000,
                 "chat":{
                    "id":101,
-                   "username":"user321",
+                   "username":"user123",
                    "first_name":"firstname",
                    "last_name":"lastname",
                    "type": "private"
@@ -272,7 +648,41 @@ mod tests {
                 "message_id":9999,
                 "from":{
                    "id":99,
-                   "username":"firstlast",
+                   "username":"user123",
+                   "first_name":"firstname",
+                   "last_name":"lastname",
+                   "type": "private",
+                   "is_bot": false
+                },
+                "text":"/balance"
+              }
+            }"#;
+        let update = serde_json::from_str::<Update>(json).unwrap();
+        let (id, from_user, message) =
+            TelegramService::parse_update(update, &[], "grinbot123").unwrap();
+        assert_eq!(
+            Action::NoUsername(101),
+            get_action(id, &from_user, message, &users())
+        );
+    }
+
+    #[test]
+    fn raw_wrong_user_id() {
+        let json = r#"{
+            "update_id":999999,
+              "message":{
+                "date": 1568300000,
+                "chat":{
+                   "id":101,
+                   "username":"user123",
+                   "first_name":"firstname",
+                   "last_name":"lastname",
+                   "type": "private"
+                },
+                "message_id":9999,
+                "from":{
+                   "id":101,
+                   "username":"user123",
                    "first_name":"firstname",
                    "last_name":"lastname",
                    "type": "private",
@@ -282,11 +692,118 @@ mod tests {
               }
             }"#;
         let update = serde_json::from_str::<Update>(json).unwrap();
-        let (id, from_user, message) = TelegramService::parse_update(update);
+        let (id, from_user, message) =
+            TelegramService::parse_update(update, &[], "grinbot123").unwrap();
         assert_eq!(
             Action::WrongUsername(101),
-            get_action(id, &from_user, message, &"user123".to_string())
+            get_action(id, &from_user, message, &users())
         );
     }
 
+    fn group_update(text: &str) -> Update {
+        let json = format!(
+            r#"{{
+            "update_id":999999,
+              "message":{{
+                "date": 1568300000,
+                "chat":{{
+                   "id":-100,
+                   "title":"treasury",
+                   "all_members_are_administrators": false,
+                   "type": "group"
+                }},
+                "message_id":9999,
+                "from":{{
+                   "id":99,
+                   "username":"user123",
+                   "first_name":"firstname",
+                   "is_bot": false
+                }},
+                "text":"{}"
+              }}
+            }}"#,
+            text
+        );
+        serde_json::from_str::<Update>(&json).unwrap()
+    }
+
+    #[test]
+    fn raw_group_update() {
+        // Only commands addressed to the bot are answered.
+        assert_eq!(
+            TelegramService::parse_update(group_update("/balance"), &[-100], "grinbot123"),
+            None
+        );
+        let (id, from_user, message) = TelegramService::parse_update(
+            group_update("/balance@GrinBot123"),
+            &[-100],
+            "grinbot123",
+        )
+        .unwrap();
+        assert_eq!(
+            Action::Balance(-100),
+            get_action(id, &from_user, message, &users())
+        );
+
+        // Groups must be allowlisted.
+        let (id, from_user, message) =
+            TelegramService::parse_update(group_update("/balance@grinbot123"), &[], "grinbot123")
+                .unwrap();
+        assert_eq!(
+            Action::ModeNotSupported(-100),
+            get_action(id, &from_user, message, &users())
+        );
+    }
+
+    #[test]
+    fn group_secrets() {
+        let route = |text: &str| {
+            let (id, from_user, message) =
+                TelegramService::parse_update(group_update(text), &[-100], "grinbot123").unwrap();
+            let private = from_user.as_ref() == Some(&id.to_string());
+            TelegramService::route(id, &from_user, message, &users(), private)
+        };
+        // The backup quiz is answered with recovery phrase words, so it only runs in private.
+        assert!(route("/backup@grinbot123").is_err());
+        assert!(route("/backup@grinbot123 ability absent").is_err());
+        // Other secrets are sent to the sender's private chat.
+        let (action, notice) = route("/create@grinbot123").unwrap();
+        assert_eq!(action, Action::Create(99, None));
+        assert!(notice.is_some());
+        assert_eq!(
+            route("/balance@grinbot123"),
+            Ok((Action::Balance(-100), None))
+        );
+    }
+
+    #[test]
+    fn inline_commands_are_read_only() {
+        assert_eq!(TelegramService::inline_command("balance"), Some("/balance"));
+        assert_eq!(
+            TelegramService::inline_command(" address"),
+            Some("/address")
+        );
+        assert_eq!(TelegramService::inline_command("send 1 alice"), None);
+    }
+
+    #[test]
+    fn messages_to_delete_survive_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_str().unwrap();
+        let first = ExpiringMessage {
+            chat: 99,
+            message: 7,
+            deadline: unix_now() + 60,
+        };
+        let second = ExpiringMessage {
+            message: 8,
+            ..first.clone()
+        };
+
+        TelegramService::remember(data_dir, &first).unwrap();
+        TelegramService::remember(data_dir, &second).unwrap();
+        TelegramService::forget(data_dir, &first);
+        let left: Vec<ExpiringMessage> = storage::load(data_dir, EXPIRING_FILE).unwrap();
+        assert_eq!(left, vec![second]);
+    }
 }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use telegram_bot::types::requests::{
+    Error, HttpRequest, JsonRequestType, JsonTrueToUnitResponse, Request, RequestType, RequestUrl,
+};
+use telegram_bot::{InlineQueryId, InlineQueryResult};
+
+/// answerInlineQuery for results only meant for the user who asked.
+/// telegram-bot's AnswerInlineQuery can't turn off Telegram's shared result cache.
+#[derive(Serialize, Debug)]
+pub struct AnswerPersonalInlineQuery {
+    inline_query_id: InlineQueryId,
+    results: Vec<InlineQueryResult>,
+    cache_time: i64,
+    is_personal: bool,
+}
+
+impl AnswerPersonalInlineQuery {
+    pub fn new(inline_query_id: InlineQueryId, results: Vec<InlineQueryResult>) -> Self {
+        AnswerPersonalInlineQuery {
+            inline_query_id,
+            results,
+            cache_time: 0,
+            is_personal: true,
+        }
+    }
+}
+
+impl Request for AnswerPersonalInlineQuery {
+    type Type = JsonRequestType<Self>;
+    type Response = JsonTrueToUnitResponse;
+
+    fn serialize(&self) -> Result<HttpRequest, Error> {
+        <Self::Type as RequestType>::serialize(RequestUrl::method("answerInlineQuery"), self)
+    }
+}
+
+/// Opt-in Telegram features from config.yml.
+#[derive(Debug, Clone, Default)]
+pub struct TelegramOptions {
+    /// Group chats the bot answers addressed commands in.
+    pub groups: Vec<i64>,
+    /// Answer read-only inline queries.
+    pub inline: bool,
+}
+
+/// A sent message with a secret and the Unix time it must be deleted at.
+/// Kept in the data directory, so a restart doesn't leave the secret in the chat.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExpiringMessage {
+    pub chat: i64,
+    pub message: i64,
+    pub deadline: u64,
+}